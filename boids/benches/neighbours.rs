@@ -0,0 +1,63 @@
+use boids::{
+    behaviour::BoidBehaviour,
+    boid::Boid,
+    grid::{brute_force_neighbours, SpatialGrid},
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use nannou::prelude::{vec2, Rect};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn flock(n: usize, frame: &Rect) -> Vec<Boid> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let behaviours: Vec<BoidBehaviour> = (0..12).map(|_| BoidBehaviour::random(&mut rng)).collect();
+    (0..n)
+        .map(|i| {
+            Boid::new(
+                behaviours[i % behaviours.len()].clone(),
+                vec2(
+                    frame.left() + rng.gen::<f32>() * frame.w(),
+                    frame.bottom() + rng.gen::<f32>() * frame.h(),
+                ),
+                vec2(1.0, 0.0),
+                &mut rng,
+            )
+        })
+        .collect()
+}
+
+/// Collect the neighbours of every boid, as `update` does once per frame
+fn neighbours(c: &mut Criterion) {
+    let frame = Rect::from_w_h(1024.0, 768.0);
+    let mut group = c.benchmark_group("neighbours");
+    for n in [100, 500, 1000, 2000] {
+        let boids = flock(n, &frame);
+
+        group.bench_with_input(BenchmarkId::new("brute_force", n), &boids, |b, boids| {
+            b.iter(|| {
+                let mut count = 0;
+                for boid in boids {
+                    count += brute_force_neighbours(boid, boids, &frame).len();
+                }
+                black_box(count)
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("grid", n), &boids, |b, boids| {
+            let mut found: Vec<&Boid> = vec![];
+            b.iter(|| {
+                let grid = SpatialGrid::new(frame, boids);
+                let mut count = 0;
+                for boid in boids {
+                    found.clear();
+                    found.extend(grid.neighbours(boid, boids));
+                    count += found.len();
+                }
+                black_box(count)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, neighbours);
+criterion_main!(benches);
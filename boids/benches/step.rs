@@ -0,0 +1,56 @@
+use boids::{
+    behaviour::{BoidBehaviour, Relationships},
+    boid::Boid,
+    flock::{Flock, World},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nannou::prelude::{vec2, Rect};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// A flock of `n` boids, in a frame that grows with it so they are as crowded at any size
+fn flock(n: usize) -> (Flock, Rect) {
+    let side = (n as f32).sqrt() * 30.0;
+    let frame = Rect::from_w_h(side, side);
+    let mut rng = StdRng::seed_from_u64(0);
+    let behaviours: Vec<BoidBehaviour> = (0..12).map(|_| BoidBehaviour::random(&mut rng)).collect();
+    let boids = (0..n)
+        .map(|i| {
+            Boid::new(
+                behaviours[i % behaviours.len()].clone(),
+                vec2(
+                    frame.left() + rng.gen::<f32>() * frame.w(),
+                    frame.bottom() + rng.gen::<f32>() * frame.h(),
+                ),
+                vec2(1.0, 0.0),
+                &mut rng,
+            )
+        })
+        .collect();
+    (Flock::new(boids, Relationships::default()), frame)
+}
+
+/// Step the whole flock, as `update` does on every physics tick. Compare runs with and without
+/// `--features parallel` to see the speedup.
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for n in [1000, 5000, 20000] {
+        let (flock, frame) = flock(n);
+        let world = World {
+            bounds: frame,
+            attractor: None,
+            obstacles: &[],
+        };
+        group.bench_with_input(BenchmarkId::from_parameter(n), &flock, |b, flock| {
+            b.iter_batched_ref(
+                || flock.clone(),
+                |flock| flock.step(&world, 1.0 / 60.0),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
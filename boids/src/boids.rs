@@ -1,14 +1,20 @@
 /// A module for the BoidBehaviour struct
 pub mod behaviour {
     use nannou::prelude::vec2;
+    use rand::Rng;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
     pub struct BoidBehaviour {
         pub debug: bool,
 
         pub group: u64,
+        #[serde(with = "sketch::serialize::rgb8")]
         pub color: nannou::color::Rgb<u8>,
         pub size: f32,
+        #[serde(with = "sketch::serialize::vec2s")]
         pub points: Vec<nannou::geom::Point2>,
 
         pub inertia: f32,
@@ -56,22 +62,16 @@ pub mod behaviour {
             self.alignment + self.cohesion + self.separation
         }
 
-        pub fn random() -> Self {
-            let neighbourhood_radius = (80.0 + 200.0 * rand::random::<f32>()).max(150.0);
-            let personal = (neighbourhood_radius / (2.0 + 3.0 * rand::random::<f32>())).min(100.0);
+        pub fn random(rng: &mut impl Rng) -> Self {
+            let neighbourhood_radius = (80.0 + 200.0 * rng.gen::<f32>()).max(150.0);
+            let personal = (neighbourhood_radius / (2.0 + 3.0 * rng.gen::<f32>())).min(100.0);
             BoidBehaviour {
                 debug: false,
-                group: rand::random::<u64>(),
-                color: nannou::color::rgb(
-                    rand::random::<u8>(),
-                    rand::random::<u8>(),
-                    rand::random::<u8>(),
-                ),
-                size: (1.0 + rand::random::<f32>() * neighbourhood_radius / personal)
-                    .min(2.0)
-                    .max(1.0),
-
-                points: if rand::random::<bool>() {
+                group: rng.gen::<u64>(),
+                color: nannou::color::rgb(rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>()),
+                size: (1.0 + rng.gen::<f32>() * neighbourhood_radius / personal).clamp(1.0, 2.0),
+
+                points: if rng.gen::<bool>() {
                     vec![
                         vec2(0.0, 10.0),
                         vec2(5.0, -5.0),
@@ -92,29 +92,105 @@ pub mod behaviour {
                     ]
                 },
 
-                inertia: rand::random::<f32>().max(0.5),
-                alignment: 0.4 + 0.2 * rand::random::<f32>(),
+                inertia: rng.gen::<f32>().max(0.5),
+                alignment: 0.4 + 0.2 * rng.gen::<f32>(),
                 cohesion: 0.5,
-                separation: 0.5 + 0.1 * rand::random::<f32>(),
-                avoidance: rand::random::<f32>().max(0.5),
-                mouse_bias: rand::random::<f32>().max(0.5),
+                separation: 0.5 + 0.1 * rng.gen::<f32>(),
+                avoidance: rng.gen::<f32>().max(0.5),
+                mouse_bias: rng.gen::<f32>().max(0.5),
 
                 neighbourhood: neighbourhood_radius,
                 personal_space: personal,
-                speed: 1.5 + 2.5 * rand::random::<f32>(),
+                speed: 1.5 + 2.5 * rng.gen::<f32>(),
+            }
+        }
+    }
+
+    /// How a boid treats the boids of another group
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Relationship {
+        /// Only keep out of their personal space
+        #[default]
+        Ignore,
+        /// Align and cohere with them, as with the boid's own group
+        Flock,
+        /// Steer away from them whenever they are in the neighbourhood
+        Flee,
+        /// Steer towards them, and capture them when close enough
+        Chase,
+    }
+
+    /// What happens to a boid when a boid that chases it catches it
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Capture {
+        /// The caught boid is removed from the flock
+        #[default]
+        Remove,
+        /// The caught boid joins the group of the boid that caught it
+        Convert,
+    }
+
+    /// The relationships between groups of boids
+    ///
+    /// A group always flocks with itself, and ignores any group it has no relationship with.
+    #[derive(Debug, Clone)]
+    pub struct Relationships {
+        table: HashMap<(u64, u64), Relationship>,
+        pub capture: Capture,
+        /// How close a chasing boid has to get to catch another
+        pub capture_distance: f32,
+    }
+
+    impl Default for Relationships {
+        fn default() -> Self {
+            Relationships {
+                table: HashMap::new(),
+                capture: Capture::default(),
+                capture_distance: 8.0,
+            }
+        }
+    }
+
+    impl Relationships {
+        /// How boids of group `from` treat boids of group `to`
+        pub fn get(&self, from: u64, to: u64) -> Relationship {
+            if from == to {
+                Relationship::Flock
+            } else {
+                self.table.get(&(from, to)).copied().unwrap_or_default()
             }
         }
+
+        /// Set how boids of group `from` treat boids of group `to`
+        pub fn set(&mut self, from: u64, to: u64, relationship: Relationship) {
+            self.table.insert((from, to), relationship);
+        }
+
+        /// Make `predator` chase `prey`, and `prey` flee from `predator`
+        pub fn hunt(&mut self, predator: u64, prey: u64) {
+            self.set(predator, prey, Relationship::Chase);
+            self.set(prey, predator, Relationship::Flee);
+        }
     }
 }
 
 /// A module for the Boid struct
 pub mod boid {
-    use super::behaviour::BoidBehaviour;
-    use nannou::{
-        prelude::{vec2, Rect, Vec2},
-        App,
-    };
+    use super::behaviour::{BoidBehaviour, Relationship, Relationships};
+    use crate::flock::{Polarity, World};
+    use nannou::prelude::{vec2, Rect, Vec2, Vec2Rotate, PI};
+    use rand::Rng;
+    use sketch::scene::Scene;
     const VECTOR_SCALE: f32 = 10.0;
+    /// Velocities are in units per step at this rate (in Hz), the frame rate the boids were
+    /// tuned at
+    pub const REFERENCE_RATE: f32 = 60.0;
+    /// How many steps ahead a boid looks for obstacles, on top of its personal space
+    const LOOK_AHEAD: f32 = 20.0;
+    /// The angles (from the direction of travel) that a boid looks for obstacles along
+    const FEELERS: [f32; 5] = [0.0, PI / 6.0, -PI / 6.0, PI / 3.0, -PI / 3.0];
     #[derive(Debug)]
     pub struct Boid {
         id: usize,
@@ -122,6 +198,8 @@ pub mod boid {
         pub behaviour: BoidBehaviour,
 
         pub position: Vec2,
+        /// Where the boid was before its last step, to draw it between steps
+        pub previous_position: Vec2,
         pub velocity: Vec2,
 
         pub alignment_vec: Vec2,
@@ -138,6 +216,7 @@ pub mod boid {
                 id: self.id,
                 behaviour: self.behaviour.clone(),
                 position: self.position,
+                previous_position: self.previous_position,
                 velocity: self.velocity,
                 alignment_vec: self.alignment_vec,
                 cohesion_vec: self.cohesion_vec,
@@ -149,11 +228,17 @@ pub mod boid {
     }
 
     impl Boid {
-        pub fn new(behaviour: BoidBehaviour, position: Vec2, velocity: Vec2) -> Self {
+        pub fn new(
+            behaviour: BoidBehaviour,
+            position: Vec2,
+            velocity: Vec2,
+            rng: &mut impl Rng,
+        ) -> Self {
             Boid {
-                id: rand::random::<usize>(),
+                id: rng.gen::<usize>(),
                 behaviour,
                 position,
+                previous_position: position,
                 velocity,
                 alignment_vec: vec2(0.0, 0.0),
                 cohesion_vec: vec2(0.0, 0.0),
@@ -163,9 +248,17 @@ pub mod boid {
             }
         }
 
-        pub fn draw_debug(&self, draw: &nannou::draw::Draw) {
-            draw.ellipse()
-                .x_y(self.position.x, self.position.y)
+        /// Where the boid is drawn when `alpha` of the way from its previous position to its
+        /// current one
+        pub fn interpolated_position(&self, alpha: f32) -> Vec2 {
+            self.previous_position.lerp(self.position, alpha)
+        }
+
+        pub fn draw_debug(&self, scene: &mut Scene, alpha: f32) {
+            let position = self.interpolated_position(alpha);
+            scene
+                .ellipse()
+                .x_y(position.x, position.y)
                 .w_h(
                     self.behaviour.personal_space * 2.0,
                     self.behaviour.personal_space * 2.0,
@@ -174,8 +267,9 @@ pub mod boid {
                 .stroke_weight(1.0)
                 .no_fill();
 
-            draw.ellipse()
-                .x_y(self.position.x, self.position.y)
+            scene
+                .ellipse()
+                .x_y(position.x, position.y)
                 .w_h(
                     self.behaviour.neighbourhood * 2.0,
                     self.behaviour.neighbourhood * 2.0,
@@ -184,51 +278,57 @@ pub mod boid {
                 .stroke_weight(1.0)
                 .no_fill();
 
-            draw.arrow()
-                .start(self.position)
-                .end(self.position + self.alignment_vec * VECTOR_SCALE)
+            scene
+                .arrow()
+                .start(position)
+                .end(position + self.alignment_vec * VECTOR_SCALE)
                 .weight(2.0)
                 .head_length(5.0)
                 .head_width(5.0)
                 .color(nannou::color::RED);
 
-            draw.arrow()
-                .start(self.position)
-                .end(self.position + self.cohesion_vec * VECTOR_SCALE)
+            scene
+                .arrow()
+                .start(position)
+                .end(position + self.cohesion_vec * VECTOR_SCALE)
                 .weight(2.0)
                 .head_length(5.0)
                 .head_width(5.0)
                 .color(nannou::color::GREEN);
 
-            draw.arrow()
-                .start(self.position)
-                .end(self.position + self.separation_vec * VECTOR_SCALE)
+            scene
+                .arrow()
+                .start(position)
+                .end(position + self.separation_vec * VECTOR_SCALE)
                 .weight(2.0)
                 .head_length(5.0)
                 .head_width(5.0)
                 .color(nannou::color::BLUE);
 
-            draw.arrow()
-                .start(self.position)
-                .end(self.position + self.avoidance_vec * VECTOR_SCALE)
+            scene
+                .arrow()
+                .start(position)
+                .end(position + self.avoidance_vec * VECTOR_SCALE)
                 .weight(2.0)
                 .head_length(5.0)
                 .head_width(5.0)
                 .color(nannou::color::YELLOW);
 
-            draw.arrow()
-                .start(self.position)
-                .end(self.position + self.bias * VECTOR_SCALE)
+            scene
+                .arrow()
+                .start(position)
+                .end(position + self.bias * VECTOR_SCALE)
                 .weight(2.0)
                 .head_length(5.0)
                 .head_width(5.0)
                 .color(nannou::color::PURPLE);
 
-            self._draw(draw);
+            self._draw(scene, position);
         }
 
-        fn _draw(&self, draw: &nannou::draw::Draw) {
-            draw.polygon()
+        fn _draw(&self, scene: &mut Scene, position: Vec2) {
+            scene
+                .polygon()
                 .points(
                     self.behaviour
                         .points
@@ -237,76 +337,130 @@ pub mod boid {
                         .map(|p| p * self.behaviour.size)
                         .collect::<Vec<Vec2>>(),
                 )
-                .x_y(self.position.x, self.position.y)
+                .x_y(position.x, position.y)
                 .rotate(-self.velocity.angle_between(vec2(0.0, 1.0)))
                 .color(self.behaviour.color);
         }
 
-        pub fn draw(&self, draw: &nannou::draw::Draw) {
+        /// Draw the boid `alpha` of the way through its last step
+        pub fn draw(&self, scene: &mut Scene, alpha: f32) {
             if self.behaviour.debug {
-                self.draw_debug(draw);
+                self.draw_debug(scene, alpha);
             } else {
-                self._draw(draw);
+                self._draw(scene, self.interpolated_position(alpha));
             }
         }
+
         /// Calculate the alignment vector for a boid
-        /// Alignment is the vector that points in the average direction of the local boids
-        fn align(&self, boids: &Vec<Boid>) -> Vec2 {
+        /// Alignment is the vector that points in the average direction of the local boids it flocks with
+        fn align(&self, boids: &[&Boid], relationships: &Relationships) -> Vec2 {
             let mut alignment = vec2(0.0, 0.0);
             for boid in boids {
-                if self.behaviour.group == boid.behaviour.group {
+                if relationships.get(self.behaviour.group, boid.behaviour.group)
+                    == Relationship::Flock
+                {
                     alignment += boid.velocity / self.behaviour.speed.powf(boids.len() as f32);
                 }
             }
-            return alignment;
+            alignment
         }
 
         /// Calculate the clustering vector for a boid
-        /// Cohesion is the vector that points towards the center of mass of the local boids of the same group
-        fn cohere(&self, boids: &Vec<Boid>, frame: &Rect) -> Vec2 {
+        /// Cohesion is the vector that points towards the center of mass of the local boids it flocks with,
+        /// and towards the boids it chases
+        fn cohere(&self, boids: &[&Boid], frame: &Rect, relationships: &Relationships) -> Vec2 {
             let mut coherence = vec2(0.0, 0.0);
             for boid in boids {
-                if boid.behaviour.group == self.behaviour.group {
-                    let vec = self.get_vector_to(boid, &frame);
-                    let distance = vec.length();
-                    coherence += (boid.position - self.position).normalize()
-                        * (distance / (self.behaviour.neighbourhood * boids.len() as f32)).powi(3);
+                let vec = self.get_vector_to(boid, frame);
+                let distance = vec.length();
+                match relationships.get(self.behaviour.group, boid.behaviour.group) {
+                    Relationship::Flock => {
+                        coherence += (boid.position - self.position).normalize()
+                            * (distance / (self.behaviour.neighbourhood * boids.len() as f32))
+                                .powi(3);
+                    }
+                    Relationship::Chase => {
+                        coherence += -vec.normalize_or_zero()
+                            * (1.0 - distance / self.behaviour.neighbourhood);
+                    }
+                    Relationship::Ignore | Relationship::Flee => (),
                 }
             }
-            return coherence;
+            coherence
         }
 
         /// Calculate the separation vector for a boid
-        /// Separation is the vector that points away from the closest local boid
-        fn separate(&self, boids: &Vec<Boid>, frame: &Rect) -> Vec2 {
+        /// Separation is the vector that points away from the closest local boid, and from
+        /// every boid it flees
+        fn separate(&self, boids: &[&Boid], frame: &Rect, relationships: &Relationships) -> Vec2 {
             let mut separation = vec2(0.0, 0.0);
             for boid in boids {
-                let vec = self.get_vector_to(boid, &frame);
+                let vec = self.get_vector_to(boid, frame);
                 let distance = vec.length();
-                separation += vec.normalize()
-                    * (1f32 / (distance - self.behaviour.personal_space).max(1f32 / 5f32));
+                match relationships.get(self.behaviour.group, boid.behaviour.group) {
+                    Relationship::Ignore | Relationship::Flock => {
+                        separation += vec.normalize()
+                            * (1f32 / (distance - self.behaviour.personal_space).max(1f32 / 5f32));
+                    }
+                    Relationship::Flee => {
+                        separation += vec.normalize_or_zero()
+                            * (self.behaviour.neighbourhood / distance.max(1.0));
+                    }
+                    // Don't keep away from the boids being chased
+                    Relationship::Chase => (),
+                }
             }
 
-            return separation;
+            separation
         }
 
         /// Calculate the bias vector for a boid
-        /// Bias is the vector that points towards the mouse
-        fn bias(&self, app: &App) -> Vec2 {
-            let mouse = app.mouse.position();
-            if app.mouse.buttons.left().is_down() {
-                return (mouse - self.position).normalize();
-            } else if app.mouse.buttons.right().is_down() {
-                return (self.position - mouse).normalize();
+        /// Bias is the vector that points towards (or away from) the attractor
+        fn bias(&self, world: &World) -> Vec2 {
+            match world.attractor {
+                Some((point, Polarity::Attract)) => (point - self.position).normalize(),
+                Some((point, Polarity::Repel)) => (self.position - point).normalize(),
+                None => vec2(0.0, 0.0),
+            }
+        }
+
+        /// Calculate the avoidance vector for a boid
+        /// Avoidance is the vector that points away from the obstacles ahead of the boid,
+        /// growing stronger the closer they are
+        fn avoid(&self, world: &World) -> Vec2 {
+            let heading = self.velocity.normalize_or_zero();
+            if heading == Vec2::ZERO {
+                return vec2(0.0, 0.0);
+            }
+            let look_ahead = self.behaviour.personal_space + self.behaviour.speed * LOOK_AHEAD;
+            let mut avoidance = vec2(0.0, 0.0);
+            for angle in FEELERS {
+                let direction = heading.rotate(angle);
+                let nearest = world
+                    .obstacles
+                    .iter()
+                    .filter_map(|obstacle| obstacle.ray_cast(self.position, direction, look_ahead))
+                    .min_by(|a, b| a.distance.total_cmp(&b.distance));
+                if let Some(hit) = nearest {
+                    avoidance += hit.normal * (1.0 - hit.distance / look_ahead);
+                }
             }
-            return vec2(0.0, 0.0);
+            avoidance
         }
 
-        pub fn update(&mut self, app: &App, boids: &Vec<Boid>) {
-            let frame = app.window_rect();
+        /// Move the boid one step of `dt` seconds and steer it using its local `boids`
+        pub fn update(
+            &mut self,
+            world: &World,
+            relationships: &Relationships,
+            boids: &[&Boid],
+            dt: f32,
+        ) {
+            let frame = world.bounds;
 
             // Wrap around the screen
-            self.position += self.velocity;
+            self.previous_position = self.position;
+            self.position += self.velocity * dt * REFERENCE_RATE;
             if self.position.x < frame.left() - 15.0 {
                 self.position.x = frame.right() - 1.0;
             } else if self.position.x > frame.right() + 15.0 {
@@ -317,43 +471,40 @@ pub mod boid {
             } else if self.position.y > frame.top() + 15.0 {
                 self.position.y = frame.bottom() + 1.0;
             }
-
-            // Get the local boids
-            let boids: Vec<Boid> = boids
-                .iter()
-                .filter(|boid| {
-                    self.position.distance(boid.position) < self.behaviour.neighbourhood
-                        && boid.id != self.id // Don't include self
-                })
-                .map(|boid| boid.clone())
-                .collect();
+            if self.position.distance(self.previous_position) > frame.w().min(frame.h()) / 2.0 {
+                // Don't draw the boid sweeping across the screen when it wraps
+                self.previous_position = self.position;
+            }
 
             // Calculate the vectors
-            if boids.len() > 0 {
-                self.alignment_vec = self.align(&boids);
-                self.cohesion_vec = self.cohere(&boids, &frame);
-                self.separation_vec = self.separate(&boids, &frame);
+            if !boids.is_empty() {
+                self.alignment_vec = self.align(boids, relationships);
+                self.cohesion_vec = self.cohere(boids, &frame, relationships);
+                self.separation_vec = self.separate(boids, &frame, relationships);
             } else {
                 self.alignment_vec = vec2(0.0, 0.0);
                 self.cohesion_vec = vec2(0.0, 0.0);
                 self.separation_vec = vec2(0.0, 0.0);
             }
-            // self.avoidance_vec = self.avoid(app);
-            self.bias = self.bias(app);
+            self.avoidance_vec = self.avoid(world);
+            self.bias = self.bias(world);
 
-            // Update the velocity
-            self.velocity = (self.velocity.normalize_or_zero() * self.behaviour.inertia
+            // Update the velocity, turning as far in this step as the boids did in a step at the
+            // reference rate
+            let steered = (self.velocity.normalize_or_zero() * self.behaviour.inertia
                 + self.alignment_vec.normalize_or_zero() * self.behaviour.alignment
                 + self.cohesion_vec.normalize_or_zero() * self.behaviour.cohesion
                 + self.separation_vec.normalize_or_zero() * self.behaviour.separation
-                // + self.avoidance_vec.normalize_or_zero() * self.behaviour.avoidance
+                + self.avoidance_vec.normalize_or_zero() * self.behaviour.avoidance
                 + self.bias.normalize_or_zero() * self.behaviour.mouse_bias)
                 // .normalize_or_zero()
                 * self.behaviour.speed
                 / self.behaviour.weight();
+            self.velocity = self.velocity.lerp(steered, (dt * REFERENCE_RATE).min(1.0));
         }
 
-        fn get_vector_to(&self, to: &Boid, frame: &Rect) -> Vec2 {
+        /// The shortest vector from `to` to this boid, wrapping around the edges of `frame`
+        pub fn get_vector_to(&self, to: &Boid, frame: &Rect) -> Vec2 {
             let d_y = self.position.y - to.position.y;
             let d_y = if d_y > frame.h() / 2.0 {
                 d_y - frame.h()
@@ -374,3 +525,197 @@ pub mod boid {
         }
     }
 }
+
+/// A module for the SpatialGrid struct
+pub mod grid {
+    use super::boid::Boid;
+    use nannou::prelude::{Rect, Vec2};
+
+    /// How many cells span the largest neighbourhood in the flock
+    const CELLS_PER_NEIGHBOURHOOD: f32 = 3.0;
+
+    /// A uniform grid over the window that buckets boids by position, so that neighbour
+    /// lookups only have to check the cells around a boid instead of the whole flock.
+    ///
+    /// The grid wraps around the edges of the frame in the same way as `Boid::get_vector_to`.
+    #[derive(Debug)]
+    pub struct SpatialGrid {
+        frame: Rect,
+        cell_size: Vec2,
+        cols: usize,
+        rows: usize,
+        /// `cells[cell_start[i]..cell_start[i + 1]]` are the indices of the boids in cell `i`
+        cell_start: Vec<usize>,
+        cells: Vec<usize>,
+    }
+
+    impl SpatialGrid {
+        /// Build a grid over `frame` for `boids`.
+        ///
+        /// Cells are a fraction of the largest neighbourhood in the flock, so a query only
+        /// checks the boids in a block of cells just covering the neighbourhood.
+        pub fn new(frame: Rect, boids: &[Boid]) -> Self {
+            let min_cell_size = boids
+                .iter()
+                .fold(1.0, |acc: f32, boid| acc.max(boid.behaviour.neighbourhood))
+                / CELLS_PER_NEIGHBOURHOOD;
+            let cols = ((frame.w() / min_cell_size).floor() as usize).max(1);
+            let rows = ((frame.h() / min_cell_size).floor() as usize).max(1);
+            let mut grid = SpatialGrid {
+                frame,
+                cell_size: Vec2::new(frame.w() / cols as f32, frame.h() / rows as f32),
+                cols,
+                rows,
+                cell_start: vec![0; cols * rows + 1],
+                cells: vec![0; boids.len()],
+            };
+
+            // Counting sort of the boids into their cells
+            let keys: Vec<usize> = boids
+                .iter()
+                .map(|boid| grid.cell_of(boid.position))
+                .collect();
+            for &key in &keys {
+                grid.cell_start[key + 1] += 1;
+            }
+            for i in 1..grid.cell_start.len() {
+                grid.cell_start[i] += grid.cell_start[i - 1];
+            }
+            let mut next = grid.cell_start.clone();
+            for (i, &key) in keys.iter().enumerate() {
+                grid.cells[next[key]] = i;
+                next[key] += 1;
+            }
+            grid
+        }
+
+        /// The column and row of the cell containing `position`, wrapped into the grid
+        fn cell_coords(&self, position: Vec2) -> (usize, usize) {
+            let col = ((position.x - self.frame.left()) / self.cell_size.x).floor() as i64;
+            let row = ((position.y - self.frame.bottom()) / self.cell_size.y).floor() as i64;
+            (
+                col.rem_euclid(self.cols as i64) as usize,
+                row.rem_euclid(self.rows as i64) as usize,
+            )
+        }
+
+        fn cell_of(&self, position: Vec2) -> usize {
+            let (col, row) = self.cell_coords(position);
+            row * self.cols + col
+        }
+
+        /// Iterate over the boids within `boid.behaviour.neighbourhood` of `boid`, excluding
+        /// `boid` itself. `boids` must be the same slice the grid was built from.
+        pub fn neighbours<'g, 'a: 'g>(
+            &'g self,
+            boid: &'a Boid,
+            boids: &'a [Boid],
+        ) -> impl Iterator<Item = &'a Boid> + 'g {
+            let radius = boid.behaviour.neighbourhood;
+            let (col, row) = self.cell_coords(boid.position);
+
+            // Never visit a cell twice, even if the neighbourhood is wider than the frame
+            let reach_x = (radius / self.cell_size.x).ceil() as usize;
+            let reach_y = (radius / self.cell_size.y).ceil() as usize;
+            let span_x = (2 * reach_x + 1).min(self.cols);
+            let span_y = (2 * reach_y + 1).min(self.rows);
+            let first_col = col + self.cols - reach_x.min(self.cols / 2);
+            let first_row = row + self.rows - reach_y.min(self.rows / 2);
+
+            (0..span_y)
+                .flat_map(move |dy| (0..span_x).map(move |dx| (dx, dy)))
+                .flat_map(move |(dx, dy)| {
+                    let cell =
+                        ((first_row + dy) % self.rows) * self.cols + (first_col + dx) % self.cols;
+                    self.cells[self.cell_start[cell]..self.cell_start[cell + 1]].iter()
+                })
+                .map(move |&i| &boids[i])
+                .filter(move |other| {
+                    !std::ptr::eq(*other, boid)
+                        && boid.get_vector_to(other, &self.frame).length_squared() < radius * radius
+                })
+        }
+    }
+
+    /// Find the neighbours of `boid` by checking every other boid in the flock.
+    ///
+    /// This is what `SpatialGrid::neighbours` replaces, and is kept as a reference for it.
+    pub fn brute_force_neighbours<'a>(
+        boid: &'a Boid,
+        boids: &'a [Boid],
+        frame: &Rect,
+    ) -> Vec<&'a Boid> {
+        boids
+            .iter()
+            .filter(|other| {
+                !std::ptr::eq(*other, boid)
+                    && boid.get_vector_to(other, frame).length() < boid.behaviour.neighbourhood
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::behaviour::BoidBehaviour;
+        use nannou::prelude::vec2;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        /// The indices of `found` in `boids`, in order
+        fn indices(found: &[&Boid], boids: &[Boid]) -> Vec<usize> {
+            let mut indices: Vec<usize> = found
+                .iter()
+                .map(|boid| {
+                    boids
+                        .iter()
+                        .position(|other| std::ptr::eq(other, *boid))
+                        .unwrap()
+                })
+                .collect();
+            indices.sort();
+            indices
+        }
+
+        #[test]
+        fn grid_finds_the_same_neighbours_as_brute_force() {
+            let mut rng = StdRng::seed_from_u64(0);
+            let frame = Rect::from_w_h(800.0, 600.0);
+            for _ in 0..10 {
+                let behaviours: Vec<BoidBehaviour> =
+                    (0..4).map(|_| BoidBehaviour::random(&mut rng)).collect();
+                let mut boids: Vec<Boid> = (0..300)
+                    .map(|i| {
+                        let position = vec2(
+                            frame.left() + rng.gen::<f32>() * frame.w(),
+                            frame.bottom() + rng.gen::<f32>() * frame.h(),
+                        );
+                        Boid::new(behaviours[i % 4].clone(), position, Vec2::ZERO, &mut rng)
+                    })
+                    .collect();
+                // Crowd some boids against the edges and corners, to find each other across
+                // the wrap
+                for (boid, position) in boids.iter_mut().zip([
+                    frame.bottom_left(),
+                    frame.top_right() - vec2(0.01, 0.01),
+                    vec2(frame.left() + 1.0, 0.0),
+                    vec2(frame.right() - 1.0, 0.0),
+                    vec2(0.0, frame.bottom() + 1.0),
+                    vec2(0.0, frame.top() - 1.0),
+                ]) {
+                    boid.position = position;
+                }
+
+                let grid = SpatialGrid::new(frame, &boids);
+                for boid in &boids {
+                    let found: Vec<&Boid> = grid.neighbours(boid, &boids).collect();
+                    assert_eq!(
+                        indices(&found, &boids),
+                        indices(&brute_force_neighbours(boid, &boids, &frame), &boids),
+                        "neighbours of the boid at {}",
+                        boid.position
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    behaviour::{BoidBehaviour, Capture, Relationship, Relationships},
+    boid::Boid,
+    grid::SpatialGrid,
+    obstacle::Obstacle,
+};
+use nannou::prelude::{Rect, Vec2};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Whether an attractor pulls the boids towards it or pushes them away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    Attract,
+    Repel,
+}
+
+/// Everything outside of the flock that a step of the simulation depends on
+#[derive(Debug, Clone, Copy)]
+pub struct World<'a> {
+    /// The area the boids live in; they wrap around its edges
+    pub bounds: Rect,
+    /// A point that biases the boids towards or away from it (e.g. the mouse)
+    pub attractor: Option<(Vec2, Polarity)>,
+    /// The obstacles the boids steer around
+    pub obstacles: &'a [Obstacle],
+}
+
+/// A flock of boids that can be stepped without a window
+#[derive(Debug, Clone, Default)]
+pub struct Flock {
+    pub boids: Vec<Boid>,
+    pub relationships: Relationships,
+}
+
+impl Flock {
+    pub fn new(boids: Vec<Boid>, relationships: Relationships) -> Self {
+        Flock {
+            boids,
+            relationships,
+        }
+    }
+
+    /// Give every boid in `behaviour.group` that behaviour, e.g. after it has been tuned
+    pub fn set_behaviour(&mut self, behaviour: &BoidBehaviour) {
+        for boid in &mut self.boids {
+            if boid.behaviour.group == behaviour.group {
+                boid.behaviour = behaviour.clone();
+            }
+        }
+    }
+
+    /// Advance every boid by one step of `dt` seconds.
+    ///
+    /// Each boid is stepped against a snapshot of the flock from before the step, so the
+    /// order of the boids does not matter, and with the `parallel` feature they are stepped on
+    /// every core with the same results. Boids that are caught during the step are removed or
+    /// converted at the end of it, depending on `relationships.capture`.
+    pub fn step(&mut self, world: &World, dt: f32) {
+        let grid = SpatialGrid::new(world.bounds, &self.boids);
+        #[cfg(feature = "parallel")]
+        let stepped = self.step_in_parallel(&grid, world, dt);
+        #[cfg(not(feature = "parallel"))]
+        let stepped = self.step_in_turn(&grid, world, dt);
+
+        let (mut new_boids, caught): (Vec<Boid>, Vec<Option<&Boid>>) = stepped.into_iter().unzip();
+        let mut caught = caught.into_iter();
+        match self.relationships.capture {
+            Capture::Remove => new_boids.retain(|_| caught.next().unwrap().is_none()),
+            Capture::Convert => {
+                for (boid, predator) in new_boids.iter_mut().zip(caught) {
+                    if let Some(predator) = predator {
+                        boid.behaviour = predator.behaviour.clone();
+                    }
+                }
+            }
+        }
+        self.boids = new_boids;
+    }
+
+    /// Each boid moved on by a step, on every core
+    #[cfg(feature = "parallel")]
+    fn step_in_parallel<'a>(
+        &'a self,
+        grid: &SpatialGrid,
+        world: &World,
+        dt: f32,
+    ) -> Vec<(Boid, Option<&'a Boid>)> {
+        self.boids
+            .par_iter()
+            .map_init(Vec::new, |neighbours, boid| {
+                self.step_boid(boid, grid, neighbours, world, dt)
+            })
+            .collect()
+    }
+
+    /// Each boid moved on by a step, one after another
+    #[cfg(any(test, not(feature = "parallel")))]
+    fn step_in_turn<'a>(
+        &'a self,
+        grid: &SpatialGrid,
+        world: &World,
+        dt: f32,
+    ) -> Vec<(Boid, Option<&'a Boid>)> {
+        let mut neighbours = vec![];
+        self.boids
+            .iter()
+            .map(|boid| self.step_boid(boid, grid, &mut neighbours, world, dt))
+            .collect()
+    }
+
+    /// A copy of `boid` moved on by a step, and the boid that caught it, if any. `neighbours`
+    /// is somewhere to collect the boid's neighbours, to save allocating it for every boid.
+    fn step_boid<'a>(
+        &'a self,
+        boid: &'a Boid,
+        grid: &SpatialGrid,
+        neighbours: &mut Vec<&'a Boid>,
+        world: &World,
+        dt: f32,
+    ) -> (Boid, Option<&'a Boid>) {
+        neighbours.clear();
+        neighbours.extend(grid.neighbours(boid, &self.boids));
+        let caught = self.caught_by(boid, neighbours, world);
+        let mut new_boid = boid.clone();
+        new_boid.update(world, &self.relationships, neighbours, dt);
+        (new_boid, caught)
+    }
+
+    /// The closest of `neighbours` that chases `boid` and is close enough to catch it
+    fn caught_by<'a>(
+        &self,
+        boid: &Boid,
+        neighbours: &[&'a Boid],
+        world: &World,
+    ) -> Option<&'a Boid> {
+        neighbours
+            .iter()
+            .filter(|other| {
+                self.relationships
+                    .get(other.behaviour.group, boid.behaviour.group)
+                    == Relationship::Chase
+            })
+            .map(|other| (*other, boid.get_vector_to(other, &world.bounds).length()))
+            .filter(|(_, distance)| *distance < self.relationships.capture_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(other, _)| other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// How far past the edges a boid can be before it wraps
+    const WRAP_MARGIN: f32 = 15.0;
+
+    #[test]
+    fn a_long_run_keeps_every_boid_finite_and_in_bounds() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let behaviours: Vec<BoidBehaviour> = (1..=3)
+            .map(|group| BoidBehaviour {
+                group,
+                ..BoidBehaviour::random(&mut rng)
+            })
+            .collect();
+        let world = World {
+            bounds: Rect::from_w_h(800.0, 600.0),
+            attractor: Some((Vec2::new(100.0, -50.0), Polarity::Attract)),
+            obstacles: &[],
+        };
+        let boids: Vec<Boid> = (0..40)
+            .map(|i| {
+                let position = Vec2::new(
+                    world.bounds.left() + rng.gen::<f32>() * world.bounds.w(),
+                    world.bounds.bottom() + rng.gen::<f32>() * world.bounds.h(),
+                );
+                let velocity = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                Boid::new(behaviours[i % 3].clone(), position, velocity, &mut rng)
+            })
+            .collect();
+        // Without any chasing, no boid is ever caught
+        let mut flock = Flock::new(boids, Relationships::default());
+        let count = flock.boids.len();
+        let outer = world.bounds.pad(-WRAP_MARGIN);
+
+        for step in 0..3000 {
+            flock.step(&world, 1.0 / 60.0);
+            assert_eq!(flock.boids.len(), count, "boids lost at step {}", step);
+            for boid in &flock.boids {
+                assert!(
+                    boid.position.is_finite() && boid.velocity.is_finite(),
+                    "boid went to {} at {} on step {}",
+                    boid.position,
+                    boid.velocity,
+                    step
+                );
+                assert!(
+                    outer.contains(boid.position),
+                    "boid escaped to {} on step {}",
+                    boid.position,
+                    step
+                );
+            }
+        }
+    }
+
+    /// Stepping the boids on every core gives bit for bit the same flock as stepping them one
+    /// after another, catches and all
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn stepping_in_parallel_matches_stepping_in_turn() {
+        use crate::scenario::Scenario;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut flock = Scenario::random(&mut rng).spawn(&mut rng);
+        let world = World {
+            bounds: Rect::from_w_h(1024.0, 768.0),
+            attractor: Some((Vec2::new(-200.0, 100.0), Polarity::Repel)),
+            obstacles: &[],
+        };
+        let dt = 1.0 / 60.0;
+        // Each moved boid bit for bit, and which boid caught it
+        let state = |stepped: Vec<(Boid, Option<&Boid>)>| -> Vec<([u32; 4], Option<*const Boid>)> {
+            stepped
+                .into_iter()
+                .map(|(boid, caught)| {
+                    let (position, velocity) = (boid.position, boid.velocity);
+                    (
+                        [position.x, position.y, velocity.x, velocity.y].map(f32::to_bits),
+                        caught.map(|caught| caught as *const Boid),
+                    )
+                })
+                .collect()
+        };
+
+        for step in 0..60 {
+            let grid = SpatialGrid::new(world.bounds, &flock.boids);
+            assert_eq!(
+                state(flock.step_in_parallel(&grid, &world, dt)),
+                state(flock.step_in_turn(&grid, &world, dt)),
+                "on step {}",
+                step
+            );
+            flock.step(&world, dt);
+        }
+    }
+}
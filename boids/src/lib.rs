@@ -0,0 +1,7 @@
+mod boids;
+pub mod flock;
+pub mod obstacle;
+pub mod panel;
+pub mod scenario;
+
+pub use boids::{behaviour, boid, grid};
@@ -1,96 +1,299 @@
-mod boids;
-
-use boids::{behaviour::BoidBehaviour, boid::Boid};
+use boids::{
+    flock::{Flock, Polarity, World},
+    obstacle::Obstacle,
+    panel::{Action, Panel},
+    scenario::Scenario,
+};
+use clap::Parser;
 use nannou::prelude::*;
+use sketch::{
+    record::{self, RecordArgs, Recorder},
+    scene::Scene,
+    seed::SeedArgs,
+    timestep::FixedTimestep,
+    Sketch,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// A flocking simulation
+#[derive(Debug, Parser)]
+struct Args {
+    /// A .toml or .ron file describing the groups of boids to simulate (random groups if not given)
+    scenario: Option<PathBuf>,
+    #[command(flatten)]
+    seed: SeedArgs,
+    /// How many times a second the simulation is stepped, independent of the frame rate
+    #[arg(long, default_value_t = 60.0, value_parser = sketch::parse_positive::<f64>)]
+    physics_rate: f64,
+    /// Where the tuning panel saves the scenario (the scenario file if not given, otherwise
+    /// scenario.toml)
+    #[arg(long, value_name = "FILE")]
+    save: Option<PathBuf>,
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
 fn main() {
-    nannou::app(model).update(update).simple_window(view).run();
+    let args = Args::parse();
+    if args.record.headless {
+        if let Err(e) = record::run_headless(&mut Model::new(&args), &args.record) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    nannou::app(model)
+        .update(update)
+        .event(event)
+        .simple_window(view)
+        .run();
+}
+
+/// How far the mouse has to be dragged to make an obstacle, so that a click doesn't make one
+/// too small to see or remove
+const MIN_DRAG: f32 = 4.0;
+
+/// What the mouse does in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    /// Left button attracts the boids, right button repels them
+    Attractor,
+    /// Drag from the centre to the edge of a circle
+    Circle,
+    /// Drag between two corners of a rectangle
+    Rect,
+    /// Drag from one end of a wall to the other
+    Wall,
+}
+
+impl Tool {
+    /// The obstacle made by dragging the mouse from `from` to `to` with this tool, if the drag
+    /// was long enough to make one
+    fn obstacle(&self, from: Point2, to: Point2) -> Option<Obstacle> {
+        let drag = (to - from).abs();
+        let too_short = match self {
+            // A rectangle needs to be dragged out in both directions
+            Tool::Rect => drag.min_element() < MIN_DRAG,
+            _ => drag.length() < MIN_DRAG,
+        };
+        if too_short {
+            return None;
+        }
+        match self {
+            Tool::Attractor => None,
+            Tool::Circle => Some(Obstacle::Circle {
+                centre: from,
+                radius: from.distance(to).max(5.0),
+            }),
+            Tool::Rect => Some(Obstacle::Rect(Rect::from_corners(from, to))),
+            Tool::Wall => Some(Obstacle::Wall {
+                start: from,
+                end: to,
+            }),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct Model {
-    boids: Vec<Boid>,
+    scenario: Scenario,
+    flock: Flock,
+    timestep: FixedTimestep,
+    obstacles: Vec<Obstacle>,
+    tool: Tool,
+    /// Where the current obstacle drag started
+    drag_start: Option<Point2>,
+    /// The obstacle that the current drag would place
+    preview: Option<Obstacle>,
+    attractor: Option<(Point2, Polarity)>,
+    panel: Panel,
+    /// Where the panel saves the tuned scenario
+    save_path: PathBuf,
+    recorder: Recorder,
 }
 
-fn model(_app: &App) -> Model {
-    // let groups: Vec<(BoidBehaviour, u8)> = vec![
-    //     (BoidBehaviour::default(), 8),
-    //     (BoidBehaviour::random(), 12),
-    //     (BoidBehaviour::random(), 8),
-    //     (BoidBehaviour::random(), 8),
-    //     (BoidBehaviour::random(), 8),
-    //     (BoidBehaviour::random(), 8),
-    //     (BoidBehaviour::random(), 12),
-    //     (BoidBehaviour::random(), 24),
-        // (
-        //     BoidBehaviour {
-        //         group: 1,
-        //         color: rgb(255u8, 0u8, 0u8),
-        //         speed: 3.0,
-        //         inertia: 1.0,
-        //         avoidance: 1.0,
-        //         separation: 4.0,
-        //         alignment: 3.0,
-        //         cohesion: 1.0,
-        //         mouse_bias: 1.0,
-        //         neighbourhood: 300.0,
-        //         personal_space: 5.0,
-        //     },
-        //     8,
-        // ),
-        // (
-        //     BoidBehaviour {
-        //         group: 2,
-        //         color: rgb(0u8, 255u8, 0u8),
-        //         speed: 1.0,
-        //         inertia: 5.0,
-        //         avoidance: 1.0,
-        //         separation: 2.0,
-        //         alignment: 4.0,
-        //         cohesion: 2.0,
-        //         mouse_bias: 1.0,
-        //         neighbourhood: 200.0,
-        //         personal_space: 75.0,
-        //     },
-        //     8,
-        // ),
-    // ];
-
-    let r = 500.0;
-    let mut boids: Vec<Boid> = vec![];
-    // for (behaviour, n) in groups {
-    for _i in 0..12 {
-        let behaviour = BoidBehaviour::random();
-        let n = (rand::random::<f32>() * 12f32 + 8f32).round() as u32;
-        for i in 0..n {
-            let theta = i as f32 * PI * 2.0 / n as f32;
-            let r_theta = rand::random::<f32>() * 2.0 * PI;
-            let unit = pt2((theta).cos(), (theta).sin());
-            boids.push(Boid::new(
-                behaviour.clone(),
-                r * unit * (rand::random::<f32>() * 0.8 + 0.8),
-                pt2(r_theta.cos(), r_theta.sin()).normalize(),
-            ));
+impl Model {
+    fn new(args: &Args) -> Self {
+        let mut rng = args.seed.rng();
+        let scenario = match &args.scenario {
+            Some(path) => Scenario::load(path).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
+            }),
+            None => Scenario::random(&mut rng),
+        };
+
+        let save_path = args
+            .save
+            .clone()
+            .or_else(|| args.scenario.clone())
+            .unwrap_or_else(|| PathBuf::from("scenario.toml"));
+
+        Model {
+            flock: scenario.spawn(&mut rng),
+            scenario,
+            timestep: FixedTimestep::new(args.physics_rate),
+            obstacles: vec![],
+            tool: Tool::Attractor,
+            drag_start: None,
+            preview: None,
+            attractor: None,
+            panel: Panel::default(),
+            save_path,
+            recorder: Recorder::default(),
+        }
+    }
+}
+
+impl Model {
+    /// Whether the mouse is over the tuning panel
+    fn over_panel(&self, app: &App) -> bool {
+        self.panel.contains(
+            app.window_rect(),
+            &self.scenario.groups,
+            app.mouse.position(),
+        )
+    }
+
+    /// Carry out what a click or drag on the tuning panel asked for
+    fn apply(&mut self, action: Option<Action>) {
+        match action {
+            Some(Action::Changed(group)) => {
+                self.flock
+                    .set_behaviour(&self.scenario.groups[group].behaviour);
+            }
+            Some(Action::Save) => match self.scenario.save(&self.save_path) {
+                Ok(()) => println!("saved {}", self.save_path.display()),
+                Err(e) => eprintln!("error: {}", e),
+            },
+            None => (),
         }
     }
-    return Model { boids: boids };
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
-    let mut new_boids: Vec<Boid> = vec![];
-    for boid in &model.boids {
-        let mut new_boid = boid.clone();
-        new_boid.update(app, &model.boids);
-        new_boids.push(new_boid);
+impl Sketch for Model {
+    fn advance(&mut self, bounds: Rect, dt: Duration) {
+        let world = World {
+            bounds,
+            attractor: self.attractor,
+            obstacles: &self.obstacles,
+        };
+        for _ in 0..self.timestep.advance(dt) {
+            self.flock.step(&world, self.timestep.dt());
+        }
+    }
+
+    fn scene(&self, _bounds: Rect) -> Scene {
+        let mut scene = Scene::new();
+        scene.background(rgb(20u8, 20u8, 20u8));
+        for obstacle in self.obstacles.iter().chain(&self.preview) {
+            obstacle.draw(&mut scene);
+        }
+        let alpha = self.timestep.alpha();
+        for boid in &self.flock.boids {
+            boid.draw(&mut scene, alpha);
+        }
+        scene
     }
-    model.boids = new_boids;
+}
+
+fn model(_app: &App) -> Model {
+    let args = Args::parse();
+    let mut model = Model::new(&args);
+    model.recorder = Recorder::new(&args.record).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    });
+    model
+}
+
+fn event(app: &App, model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(event),
+        ..
+    } = event
+    {
+        match event {
+            KeyPressed(Key::Key1) => model.tool = Tool::Attractor,
+            KeyPressed(Key::Key2) => model.tool = Tool::Circle,
+            KeyPressed(Key::Key3) => model.tool = Tool::Rect,
+            KeyPressed(Key::Key4) => model.tool = Tool::Wall,
+            KeyPressed(Key::S) => model.recorder.request_svg(),
+            KeyPressed(Key::Tab) => model.panel.visible = !model.panel.visible,
+            KeyPressed(Key::Back) => {
+                model.obstacles.pop();
+            }
+            MousePressed(MouseButton::Left) if model.over_panel(app) => {
+                let action = model.panel.press(
+                    app.window_rect(),
+                    &mut model.scenario.groups,
+                    app.mouse.position(),
+                );
+                model.apply(action);
+            }
+            MousePressed(_) if model.over_panel(app) => (),
+            MousePressed(MouseButton::Left) if model.tool != Tool::Attractor => {
+                model.drag_start = Some(app.mouse.position());
+            }
+            MouseReleased(MouseButton::Left) => {
+                model.panel.release();
+                if let Some(start) = model.drag_start.take() {
+                    model
+                        .obstacles
+                        .extend(model.tool.obstacle(start, app.mouse.position()));
+                }
+            }
+            MousePressed(MouseButton::Right) if model.tool != Tool::Attractor => {
+                // Remove the obstacles under the mouse
+                let mouse = app.mouse.position();
+                model
+                    .obstacles
+                    .retain(|obstacle| !obstacle.contains(mouse, 5.0));
+            }
+            _ => (),
+        }
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    let mouse = app.mouse.position();
+    let action = model
+        .panel
+        .drag(app.window_rect(), &mut model.scenario.groups, mouse);
+    model.apply(action);
+
+    model.attractor = if model.tool != Tool::Attractor || model.over_panel(app) {
+        None
+    } else if app.mouse.buttons.left().is_down() {
+        Some((mouse, Polarity::Attract))
+    } else if app.mouse.buttons.right().is_down() {
+        Some((mouse, Polarity::Repel))
+    } else {
+        None
+    };
+    model.preview = model
+        .drag_start
+        .and_then(|start| model.tool.obstacle(start, mouse));
+
+    let dt = model.recorder.frame_time(app, update.since_last);
+    model.advance(app.window_rect(), dt);
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
-    draw.background().color(rgb(20u8, 20u8, 20u8));
-    for boid in &model.boids {
-        boid.draw(&draw);
+    let scene = model.scene(frame.rect());
+    scene.draw(&draw);
+    if let Err(e) = model.recorder.save_svg(&scene, frame.rect()) {
+        eprintln!("error: {}", e);
     }
+    // The panel is drawn over the scene, so that it isn't recorded
+    let mut panel = Scene::new();
+    model.panel.draw(
+        &mut panel,
+        frame.rect(),
+        &model.scenario.groups,
+        &model.save_path,
+    );
+    panel.draw(&draw);
     draw.to_frame(app, &frame).unwrap();
 }
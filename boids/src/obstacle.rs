@@ -0,0 +1,284 @@
+use nannou::prelude::{rgb, Rect, Vec2};
+use sketch::scene::Scene;
+
+/// Something solid in the world that the boids steer around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Obstacle {
+    Circle { centre: Vec2, radius: f32 },
+    Rect(Rect),
+    Wall { start: Vec2, end: Vec2 },
+}
+
+/// Where a ray cast first hits an obstacle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// How far along the ray the hit is (0 if the ray starts inside the obstacle)
+    pub distance: f32,
+    /// The surface normal at the hit, pointing back out of the obstacle
+    pub normal: Vec2,
+}
+
+impl Obstacle {
+    /// Cast a ray from `origin` along the unit vector `direction`, and find where it first hits
+    /// this obstacle within `max_distance`
+    pub fn ray_cast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        let hit = match *self {
+            Obstacle::Circle { centre, radius } => {
+                let offset = origin - centre;
+                let b = offset.dot(direction);
+                let c = offset.length_squared() - radius * radius;
+                if c < 0.0 {
+                    // Inside the circle, so push straight out of it
+                    return Some(Hit {
+                        distance: 0.0,
+                        normal: offset.normalize_or_zero(),
+                    });
+                }
+                let discriminant = b * b - c;
+                if b > 0.0 || discriminant < 0.0 {
+                    return None;
+                }
+                let distance = -b - discriminant.sqrt();
+                Hit {
+                    distance,
+                    normal: (offset + direction * distance) / radius,
+                }
+            }
+            Obstacle::Rect(rect) => {
+                if rect.contains(origin) {
+                    // Inside the rect, so push out through the nearest edge
+                    let edges = [
+                        (origin.x - rect.left(), Vec2::new(-1.0, 0.0)),
+                        (rect.right() - origin.x, Vec2::new(1.0, 0.0)),
+                        (origin.y - rect.bottom(), Vec2::new(0.0, -1.0)),
+                        (rect.top() - origin.y, Vec2::new(0.0, 1.0)),
+                    ];
+                    let (_, normal) = edges
+                        .into_iter()
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap();
+                    return Some(Hit {
+                        distance: 0.0,
+                        normal,
+                    });
+                }
+
+                // Slab test, remembering which axis the ray entered through
+                let mut entry = f32::NEG_INFINITY;
+                let mut exit = f32::INFINITY;
+                let mut normal = Vec2::ZERO;
+                for (o, d, min, max, axis) in [
+                    (origin.x, direction.x, rect.left(), rect.right(), Vec2::X),
+                    (origin.y, direction.y, rect.bottom(), rect.top(), Vec2::Y),
+                ] {
+                    if d == 0.0 {
+                        if o < min || o > max {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let (near, far) = ((min - o) / d, (max - o) / d);
+                    let (near, far) = (near.min(far), near.max(far));
+                    if near > entry {
+                        entry = near;
+                        normal = -axis * d.signum();
+                    }
+                    exit = exit.min(far);
+                }
+                if entry > exit || entry < 0.0 {
+                    return None;
+                }
+                Hit {
+                    distance: entry,
+                    normal,
+                }
+            }
+            Obstacle::Wall { start, end } => {
+                let along = end - start;
+                let denominator = direction.perp_dot(along);
+                // A wall with no length has no side to hit, and a ray along a wall never
+                // crosses it
+                if along.length_squared() == 0.0 || denominator.abs() < f32::EPSILON {
+                    return None;
+                }
+                let distance = (start - origin).perp_dot(along) / denominator;
+                let t = (start - origin).perp_dot(direction) / denominator;
+                if distance < 0.0 || !(0.0..=1.0).contains(&t) {
+                    return None;
+                }
+                // Walls are two sided, so face the normal towards the ray
+                let normal = along.perp().normalize_or_zero();
+                Hit {
+                    distance,
+                    normal: if normal.dot(direction) > 0.0 {
+                        -normal
+                    } else {
+                        normal
+                    },
+                }
+            }
+        };
+        (hit.distance <= max_distance).then_some(hit)
+    }
+
+    /// Whether `point` is on or inside the obstacle (within `tolerance` for walls)
+    pub fn contains(&self, point: Vec2, tolerance: f32) -> bool {
+        match *self {
+            Obstacle::Circle { centre, radius } => centre.distance(point) <= radius + tolerance,
+            Obstacle::Rect(rect) => rect.pad(-tolerance).contains(point),
+            Obstacle::Wall { start, end } => {
+                let along = end - start;
+                if along.length_squared() == 0.0 {
+                    return start.distance(point) <= tolerance;
+                }
+                let t = ((point - start).dot(along) / along.length_squared()).clamp(0.0, 1.0);
+                (start + along * t).distance(point) <= tolerance
+            }
+        }
+    }
+
+    pub fn draw(&self, scene: &mut Scene) {
+        let fill = rgb(60u8, 60u8, 60u8);
+        let stroke = nannou::color::GRAY;
+        match *self {
+            Obstacle::Circle { centre, radius } => {
+                scene
+                    .ellipse()
+                    .xy(centre)
+                    .radius(radius)
+                    .color(fill)
+                    .stroke(stroke)
+                    .stroke_weight(1.0);
+            }
+            Obstacle::Rect(rect) => {
+                scene
+                    .rect()
+                    .xy(rect.xy())
+                    .wh(rect.wh())
+                    .color(fill)
+                    .stroke(stroke)
+                    .stroke_weight(1.0);
+            }
+            Obstacle::Wall { start, end } => {
+                scene
+                    .line()
+                    .start(start)
+                    .end(end)
+                    .weight(4.0)
+                    .caps_round()
+                    .color(stroke);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_hit(hit: Option<Hit>, distance: f32, normal: Vec2) {
+        let hit = hit.expect("the ray should hit");
+        assert!(
+            (hit.distance - distance).abs() < 1e-4,
+            "hit at {} instead of {}",
+            hit.distance,
+            distance
+        );
+        assert!(
+            hit.normal.distance(normal) < 1e-4,
+            "normal {} instead of {}",
+            hit.normal,
+            normal
+        );
+    }
+
+    #[test]
+    fn rays_hit_circles_on_their_near_side() {
+        let circle = Obstacle::Circle {
+            centre: Vec2::new(10.0, 0.0),
+            radius: 2.0,
+        };
+        assert_hit(
+            circle.ray_cast(Vec2::ZERO, Vec2::X, 100.0),
+            8.0,
+            Vec2::new(-1.0, 0.0),
+        );
+        // Pointing away, passing by and out of reach
+        assert_eq!(circle.ray_cast(Vec2::ZERO, -Vec2::X, 100.0), None);
+        assert_eq!(circle.ray_cast(Vec2::new(0.0, 3.0), Vec2::X, 100.0), None);
+        assert_eq!(circle.ray_cast(Vec2::ZERO, Vec2::X, 5.0), None);
+        // From inside, straight out
+        assert_hit(
+            circle.ray_cast(Vec2::new(11.0, 0.0), Vec2::Y, 100.0),
+            0.0,
+            Vec2::X,
+        );
+    }
+
+    #[test]
+    fn rays_hit_rects_through_the_side_they_enter() {
+        let rect = Obstacle::Rect(Rect::from_corners(
+            Vec2::new(10.0, -5.0),
+            Vec2::new(20.0, 5.0),
+        ));
+        assert_hit(
+            rect.ray_cast(Vec2::ZERO, Vec2::X, 100.0),
+            10.0,
+            Vec2::new(-1.0, 0.0),
+        );
+        assert_hit(
+            rect.ray_cast(Vec2::new(15.0, 20.0), -Vec2::Y, 100.0),
+            15.0,
+            Vec2::Y,
+        );
+        // Diagonally, entering through the bottom
+        let direction = Vec2::new(1.0, 1.0).normalize();
+        assert_hit(
+            rect.ray_cast(Vec2::new(5.0, -15.0), direction, 100.0),
+            10.0 * 2f32.sqrt(),
+            Vec2::new(0.0, -1.0),
+        );
+        // Parallel to a side outside the slab, and pointing away
+        assert_eq!(rect.ray_cast(Vec2::new(0.0, 6.0), Vec2::X, 100.0), None);
+        assert_eq!(rect.ray_cast(Vec2::ZERO, -Vec2::X, 100.0), None);
+        // From inside, out through the nearest edge
+        assert_hit(
+            rect.ray_cast(Vec2::new(19.0, 0.0), Vec2::Y, 100.0),
+            0.0,
+            Vec2::X,
+        );
+    }
+
+    #[test]
+    fn rays_hit_walls_from_either_side() {
+        let wall = Obstacle::Wall {
+            start: Vec2::new(10.0, -5.0),
+            end: Vec2::new(10.0, 5.0),
+        };
+        assert_hit(
+            wall.ray_cast(Vec2::ZERO, Vec2::X, 100.0),
+            10.0,
+            Vec2::new(-1.0, 0.0),
+        );
+        assert_hit(
+            wall.ray_cast(Vec2::new(20.0, 0.0), -Vec2::X, 100.0),
+            10.0,
+            Vec2::X,
+        );
+        // Past its end, along it and behind the ray
+        assert_eq!(wall.ray_cast(Vec2::new(0.0, 6.0), Vec2::X, 100.0), None);
+        assert_eq!(wall.ray_cast(Vec2::new(10.0, -10.0), Vec2::Y, 100.0), None);
+        assert_eq!(wall.ray_cast(Vec2::ZERO, -Vec2::X, 100.0), None);
+    }
+
+    #[test]
+    fn a_wall_with_no_length_is_a_point() {
+        let wall = Obstacle::Wall {
+            start: Vec2::new(10.0, 0.0),
+            end: Vec2::new(10.0, 0.0),
+        };
+        assert!(wall.contains(Vec2::new(12.0, 0.0), 5.0));
+        assert!(!wall.contains(Vec2::new(20.0, 0.0), 5.0));
+        assert_eq!(wall.ray_cast(Vec2::ZERO, Vec2::X, 100.0), None);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{behaviour::BoidBehaviour, scenario::Group};
+use nannou::prelude::{pt2, rgb, rgba, Point2, Rect, GRAY, WHITE};
+use sketch::scene::Scene;
+use std::path::Path;
+
+const WIDTH: f32 = 240.0;
+const MARGIN: f32 = 10.0;
+const PADDING: f32 = 10.0;
+const ROW: f32 = 18.0;
+const SWATCH: f32 = 20.0;
+const BAR: f32 = 6.0;
+const FONT_SIZE: u32 = 12;
+
+/// A `BoidBehaviour` field that can be tuned with a slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Inertia,
+    Alignment,
+    Cohesion,
+    Separation,
+    Avoidance,
+    MouseBias,
+    Neighbourhood,
+    PersonalSpace,
+    Speed,
+}
+
+impl Field {
+    const ALL: [Field; 9] = [
+        Field::Inertia,
+        Field::Alignment,
+        Field::Cohesion,
+        Field::Separation,
+        Field::Avoidance,
+        Field::MouseBias,
+        Field::Neighbourhood,
+        Field::PersonalSpace,
+        Field::Speed,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Inertia => "inertia",
+            Field::Alignment => "alignment",
+            Field::Cohesion => "cohesion",
+            Field::Separation => "separation",
+            Field::Avoidance => "avoidance",
+            Field::MouseBias => "mouse bias",
+            Field::Neighbourhood => "neighbourhood",
+            Field::PersonalSpace => "personal space",
+            Field::Speed => "speed",
+        }
+    }
+
+    /// The slider's range. Alignment, cohesion and separation stay above zero, as a boid needs
+    /// a positive total of them to steer.
+    fn range(self) -> (f32, f32) {
+        match self {
+            Field::Inertia => (0.0, 1.0),
+            Field::Alignment | Field::Cohesion | Field::Separation => (0.01, 2.0),
+            Field::Avoidance | Field::MouseBias => (0.0, 2.0),
+            Field::Neighbourhood => (10.0, 400.0),
+            Field::PersonalSpace => (0.0, 200.0),
+            Field::Speed => (0.1, 8.0),
+        }
+    }
+
+    fn value(self, behaviour: &mut BoidBehaviour) -> &mut f32 {
+        match self {
+            Field::Inertia => &mut behaviour.inertia,
+            Field::Alignment => &mut behaviour.alignment,
+            Field::Cohesion => &mut behaviour.cohesion,
+            Field::Separation => &mut behaviour.separation,
+            Field::Avoidance => &mut behaviour.avoidance,
+            Field::MouseBias => &mut behaviour.mouse_bias,
+            Field::Neighbourhood => &mut behaviour.neighbourhood,
+            Field::PersonalSpace => &mut behaviour.personal_space,
+            Field::Speed => &mut behaviour.speed,
+        }
+    }
+}
+
+/// Where each part of the panel is
+struct Layout {
+    panel: Rect,
+    title: Rect,
+    swatches: Vec<Rect>,
+    debug: Rect,
+    /// The label row and the bar of each slider, in the order of `Field::ALL`
+    sliders: Vec<(Rect, Rect)>,
+    save: Rect,
+}
+
+impl Layout {
+    /// Lay the panel out down the top left of the window, for `groups` groups
+    fn new(bounds: Rect, groups: usize) -> Self {
+        let left = bounds.left() + MARGIN + PADDING;
+        let right = bounds.left() + MARGIN + WIDTH - PADDING;
+        let mut top = bounds.top() - MARGIN - PADDING;
+        let mut row = |height: f32, gap: f32| {
+            let rect = Rect::from_corners(pt2(left, top - height), pt2(right, top));
+            top -= height + gap;
+            rect
+        };
+
+        let title = row(ROW, 4.0);
+        let per_row = ((right - left + 4.0) / (SWATCH + 4.0)).floor().max(1.0) as usize;
+        let swatch_rows = groups.div_ceil(per_row);
+        let swatch_area = row(swatch_rows as f32 * (SWATCH + 4.0), 4.0);
+        let swatches = (0..groups)
+            .map(|i| {
+                let corner = pt2(
+                    swatch_area.left() + (i % per_row) as f32 * (SWATCH + 4.0),
+                    swatch_area.top() - (i / per_row) as f32 * (SWATCH + 4.0),
+                );
+                Rect::from_corners(corner, corner + pt2(SWATCH, -SWATCH))
+            })
+            .collect();
+        let debug = row(ROW, 4.0);
+        let sliders = Field::ALL
+            .iter()
+            .map(|_| (row(ROW, 0.0), row(BAR, 6.0)))
+            .collect();
+        let save = row(ROW + 4.0, 0.0);
+
+        let panel = Rect::from_corners(
+            pt2(bounds.left() + MARGIN, bounds.top() - MARGIN),
+            pt2(bounds.left() + MARGIN + WIDTH, save.bottom() - PADDING),
+        );
+        Layout {
+            panel,
+            title,
+            swatches,
+            debug,
+            sliders,
+            save,
+        }
+    }
+
+    /// The box of the debug checkbox, at the left of its row
+    fn checkbox(&self) -> Rect {
+        Rect::from_w_h(12.0, 12.0)
+            .align_left_of(self.debug)
+            .align_middle_y_of(self.debug)
+    }
+}
+
+/// What a click on the panel did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// The behaviour of the group at this index was changed
+    Changed(usize),
+    /// The save button was pressed
+    Save,
+}
+
+/// A panel for tuning the behaviour of each group of boids while the simulation runs, drawn
+/// with the same primitives as the boids.
+///
+/// Click a swatch to pick a group, then drag its sliders or toggle its debug drawing.
+#[derive(Debug, Clone, Default)]
+pub struct Panel {
+    pub visible: bool,
+    /// The index of the group being tuned
+    selected: usize,
+    /// The slider being dragged
+    dragging: Option<Field>,
+}
+
+impl Panel {
+    /// Whether `point` is on the panel, so that clicks there shouldn't reach the simulation
+    pub fn contains(&self, bounds: Rect, groups: &[Group], point: Point2) -> bool {
+        self.visible && Layout::new(bounds, groups.len()).panel.contains(point)
+    }
+
+    /// Handle a left click at `point`
+    pub fn press(&mut self, bounds: Rect, groups: &mut [Group], point: Point2) -> Option<Action> {
+        let layout = Layout::new(bounds, groups.len());
+        if let Some(i) = layout.swatches.iter().position(|s| s.contains(point)) {
+            self.selected = i;
+            return None;
+        }
+        if layout.save.contains(point) {
+            return Some(Action::Save);
+        }
+        let group = groups.get_mut(self.selected)?;
+        if layout.debug.contains(point) {
+            group.behaviour.debug = !group.behaviour.debug;
+            return Some(Action::Changed(self.selected));
+        }
+        let (_, field) = layout
+            .sliders
+            .iter()
+            .zip(Field::ALL)
+            .find(|((label, bar), _)| label.contains(point) || bar.contains(point))?;
+        self.dragging = Some(field);
+        self.drag(bounds, groups, point)
+    }
+
+    /// Move the slider being dragged (if any) to `point`
+    pub fn drag(&mut self, bounds: Rect, groups: &mut [Group], point: Point2) -> Option<Action> {
+        let field = self.dragging?;
+        let layout = Layout::new(bounds, groups.len());
+        let group = groups.get_mut(self.selected)?;
+        let i = Field::ALL.iter().position(|&f| f == field)?;
+        let (_, bar) = layout.sliders[i];
+        let (min, max) = field.range();
+        let t = ((point.x - bar.left()) / bar.w()).clamp(0.0, 1.0);
+        *field.value(&mut group.behaviour) = min + t * (max - min);
+        Some(Action::Changed(self.selected))
+    }
+
+    pub fn release(&mut self) {
+        self.dragging = None;
+    }
+
+    /// Draw the panel for `groups`, which are saved to `save_path`
+    pub fn draw(&self, scene: &mut Scene, bounds: Rect, groups: &[Group], save_path: &Path) {
+        if !self.visible {
+            return;
+        }
+        let layout = Layout::new(bounds, groups.len());
+        let label = |scene: &mut Scene, text: &str, rect: Rect| {
+            scene
+                .text(text)
+                .xy(rect.xy())
+                .wh(rect.wh())
+                .font_size(FONT_SIZE)
+                .left_justify()
+                .color(WHITE);
+        };
+
+        scene
+            .rect()
+            .rect(layout.panel)
+            .color(rgba(0.0, 0.0, 0.0, 0.8))
+            .stroke(GRAY)
+            .stroke_weight(1.0);
+        let title = match groups.len() {
+            0 => "no groups".to_owned(),
+            n => format!("group {} of {} (Tab to hide)", self.selected + 1, n),
+        };
+        label(scene, &title, layout.title);
+
+        for (i, (swatch, group)) in layout.swatches.iter().zip(groups).enumerate() {
+            let rect = scene.rect();
+            rect.rect(*swatch).color(group.behaviour.color);
+            if i == self.selected {
+                rect.stroke(WHITE).stroke_weight(2.0);
+            }
+        }
+
+        let Some(group) = groups.get(self.selected) else {
+            return;
+        };
+        let mut behaviour = group.behaviour.clone();
+
+        let checkbox = layout.checkbox();
+        let rect = scene.rect();
+        rect.rect(checkbox).stroke(WHITE).stroke_weight(1.0);
+        if behaviour.debug {
+            rect.color(group.behaviour.color);
+        } else {
+            rect.no_fill();
+        }
+        label(
+            scene,
+            "debug",
+            Rect::from_corners(
+                pt2(checkbox.right() + 6.0, layout.debug.bottom()),
+                layout.debug.top_right(),
+            ),
+        );
+
+        for (&(row, bar), field) in layout.sliders.iter().zip(Field::ALL) {
+            let (min, max) = field.range();
+            let value = *field.value(&mut behaviour);
+            label(scene, field.name(), row);
+            scene
+                .text(&format!("{:.2}", value))
+                .xy(row.xy())
+                .wh(row.wh())
+                .font_size(FONT_SIZE)
+                .right_justify()
+                .color(WHITE);
+            scene.rect().rect(bar).color(rgb(60u8, 60u8, 60u8));
+            let t = ((value - min) / (max - min)).clamp(0.0, 1.0);
+            let filled =
+                Rect::from_corners(bar.bottom_left(), pt2(bar.left() + bar.w() * t, bar.top()));
+            scene.rect().rect(filled).color(group.behaviour.color);
+        }
+
+        scene
+            .rect()
+            .rect(layout.save)
+            .no_fill()
+            .stroke(GRAY)
+            .stroke_weight(1.0);
+        scene
+            .text(&format!("save to {}", save_path.display()))
+            .xy(layout.save.xy())
+            .wh(layout.save.wh())
+            .font_size(FONT_SIZE)
+            .color(WHITE);
+    }
+}
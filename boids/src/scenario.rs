@@ -0,0 +1,350 @@
+use crate::{
+    behaviour::{BoidBehaviour, Capture, Relationship, Relationships},
+    boid::Boid,
+    flock::Flock,
+};
+use nannou::prelude::{Vec2, Vec2Rotate, PI};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sketch::config::{self, ConfigError};
+use std::path::Path;
+
+/// A description of a flock, that can be loaded from a TOML or RON file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub groups: Vec<Group>,
+    /// How the groups treat each other (groups not listed here ignore each other)
+    #[serde(default)]
+    pub relationships: Vec<RelationshipRule>,
+    #[serde(default)]
+    pub capture: Capture,
+    #[serde(default = "default_capture_distance")]
+    pub capture_distance: f32,
+}
+
+fn default_capture_distance() -> f32 {
+    Relationships::default().capture_distance
+}
+
+/// A number of boids that share a behaviour, and how to place them at the start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    #[serde(default)]
+    pub behaviour: BoidBehaviour,
+    pub count: usize,
+    #[serde(default)]
+    pub spawn: Spawn,
+    #[serde(default)]
+    pub velocity: Velocity,
+}
+
+/// The shape that a group of boids is placed in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Spawn {
+    /// Evenly spaced around a ring, each at a random distance from the centre
+    Ring {
+        #[serde(default, with = "sketch::serialize::vec2")]
+        centre: Vec2,
+        min_radius: f32,
+        max_radius: f32,
+    },
+    /// Anywhere in a rectangle
+    Random {
+        #[serde(default, with = "sketch::serialize::vec2")]
+        centre: Vec2,
+        width: f32,
+        height: f32,
+    },
+    /// Evenly spaced along a line
+    Line {
+        #[serde(with = "sketch::serialize::vec2")]
+        start: Vec2,
+        #[serde(with = "sketch::serialize::vec2")]
+        end: Vec2,
+    },
+}
+
+impl Default for Spawn {
+    fn default() -> Self {
+        Spawn::Ring {
+            centre: Vec2::ZERO,
+            min_radius: 400.0,
+            max_radius: 800.0,
+        }
+    }
+}
+
+/// The velocity that a group of boids starts with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Velocity {
+    /// In a random direction
+    Random { speed: f32 },
+    /// The same for every boid
+    Fixed {
+        #[serde(with = "sketch::serialize::vec2")]
+        velocity: Vec2,
+    },
+    /// Anticlockwise around the centre of the spawn shape
+    Tangential { speed: f32 },
+}
+
+impl Default for Velocity {
+    fn default() -> Self {
+        Velocity::Random { speed: 1.0 }
+    }
+}
+
+/// How the boids of one group treat the boids of another
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelationshipRule {
+    pub from: u64,
+    pub to: u64,
+    pub relationship: Relationship,
+}
+
+impl Scenario {
+    /// Load a scenario from a `.toml` or `.ron` file
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let scenario: Scenario = config::load(path)?;
+        scenario
+            .validate()
+            .map_err(|(field, message)| ConfigError::Invalid {
+                path: path.to_owned(),
+                field,
+                message: message.to_owned(),
+            })?;
+        Ok(scenario)
+    }
+
+    /// Save the scenario to a `.toml` or `.ron` file, e.g. after tuning its behaviours
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        config::save(self, path)
+    }
+
+    /// Check the values that would make the simulation misbehave, returning the path to the
+    /// first bad one and what is wrong with it
+    fn validate(&self) -> Result<(), (String, &'static str)> {
+        for (i, group) in self.groups.iter().enumerate() {
+            let behaviour = &group.behaviour;
+            let field = |name: &str| format!("groups[{}].{}", i, name);
+            if behaviour.points.len() < 3 {
+                return Err((field("behaviour.points"), "needs at least 3 points"));
+            }
+            if behaviour.weight() <= 0.0 {
+                return Err((
+                    field("behaviour"),
+                    "needs a positive alignment + cohesion + separation",
+                ));
+            }
+            if behaviour.speed <= 0.0 {
+                return Err((field("behaviour.speed"), "must be positive"));
+            }
+            if behaviour.neighbourhood <= 0.0 {
+                return Err((field("behaviour.neighbourhood"), "must be positive"));
+            }
+            if behaviour.personal_space < 0.0 {
+                return Err((field("behaviour.personal_space"), "must not be negative"));
+            }
+            if let Spawn::Ring {
+                min_radius,
+                max_radius,
+                ..
+            } = group.spawn
+            {
+                if min_radius > max_radius {
+                    return Err((field("spawn.min_radius"), "must not exceed max_radius"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Twelve random groups in rings around the centre, the first of which hunts the next two.
+    ///
+    /// The groups are numbered from 1, as TOML can't hold the larger random group numbers.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let groups: Vec<Group> = (1..=12)
+            .map(|group| Group {
+                behaviour: BoidBehaviour {
+                    group,
+                    ..BoidBehaviour::random(rng)
+                },
+                count: (rng.gen::<f32>() * 12f32 + 8f32).round() as usize,
+                spawn: Spawn::default(),
+                velocity: Velocity::default(),
+            })
+            .collect();
+        let relationships = groups[1..3]
+            .iter()
+            .flat_map(|prey| {
+                let (predator, prey) = (groups[0].behaviour.group, prey.behaviour.group);
+                [
+                    RelationshipRule {
+                        from: predator,
+                        to: prey,
+                        relationship: Relationship::Chase,
+                    },
+                    RelationshipRule {
+                        from: prey,
+                        to: predator,
+                        relationship: Relationship::Flee,
+                    },
+                ]
+            })
+            .collect();
+        Scenario {
+            groups,
+            relationships,
+            capture: Capture::Convert,
+            capture_distance: default_capture_distance(),
+        }
+    }
+
+    /// Create the flock described by the scenario
+    pub fn spawn(&self, rng: &mut impl Rng) -> Flock {
+        let mut boids: Vec<Boid> = vec![];
+        for group in &self.groups {
+            let n = group.count;
+            for i in 0..n {
+                let (position, centre) = match group.spawn {
+                    Spawn::Ring {
+                        centre,
+                        min_radius,
+                        max_radius,
+                    } => {
+                        let theta = i as f32 * PI * 2.0 / n as f32;
+                        let r = min_radius + (max_radius - min_radius) * rng.gen::<f32>();
+                        (centre + Vec2::new(theta.cos(), theta.sin()) * r, centre)
+                    }
+                    Spawn::Random {
+                        centre,
+                        width,
+                        height,
+                    } => (
+                        centre
+                            + Vec2::new(
+                                (rng.gen::<f32>() - 0.5) * width,
+                                (rng.gen::<f32>() - 0.5) * height,
+                            ),
+                        centre,
+                    ),
+                    Spawn::Line { start, end } => (
+                        start.lerp(end, (i as f32 + 0.5) / n as f32),
+                        (start + end) / 2.0,
+                    ),
+                };
+                let velocity = match group.velocity {
+                    Velocity::Random { speed } => {
+                        Vec2::new(1.0, 0.0).rotate(rng.gen::<f32>() * 2.0 * PI) * speed
+                    }
+                    Velocity::Fixed { velocity } => velocity,
+                    Velocity::Tangential { speed } => {
+                        (position - centre).perp().normalize_or_zero() * speed
+                    }
+                };
+                boids.push(Boid::new(group.behaviour.clone(), position, velocity, rng));
+            }
+        }
+
+        let mut relationships = Relationships::default();
+        for rule in &self.relationships {
+            relationships.set(rule.from, rule.to, rule.relationship);
+        }
+        relationships.capture = self.capture;
+        relationships.capture_distance = self.capture_distance;
+        Flock::new(boids, relationships)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{fs, path::PathBuf};
+
+    /// A path in the temporary directory for `name`, unique to this test run
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("boids-{}-{}", std::process::id(), name))
+    }
+
+    /// The error from loading a scenario file with `text` in it
+    fn load_error(name: &str, text: &str) -> String {
+        let path = temp_path(name);
+        fs::write(&path, text).unwrap();
+        let result = Scenario::load(&path);
+        fs::remove_file(&path).unwrap();
+        result
+            .expect_err("the scenario should not load")
+            .to_string()
+    }
+
+    #[test]
+    fn errors_name_the_field_that_is_wrong() {
+        let group = |behaviour: &str| {
+            format!(
+                "[[groups]]\ncount = 1\n\n[[groups]]\ncount = 1\n\n[groups.behaviour]\n{}\n",
+                behaviour
+            )
+        };
+        let cases = [
+            (
+                "speed = 0.0",
+                "`groups[1].behaviour.speed` must be positive",
+            ),
+            (
+                "speed = -1.0",
+                "`groups[1].behaviour.speed` must be positive",
+            ),
+            (
+                "neighbourhood = 0.0",
+                "`groups[1].behaviour.neighbourhood` must be positive",
+            ),
+            ("points = [[0.0, 1.0]]", "`groups[1].behaviour.points`"),
+            ("speed = \"fast\"", "in `groups[1].behaviour.speed`"),
+        ];
+        for (behaviour, expected) in cases {
+            let message = load_error("invalid.toml", &group(behaviour));
+            assert!(
+                message.contains(expected),
+                "{:?} from {}",
+                message,
+                behaviour
+            );
+        }
+    }
+
+    #[test]
+    fn scenarios_round_trip_through_toml_and_ron() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let scenario = Scenario::random(&mut rng);
+        for name in ["round-trip.toml", "round-trip.ron"] {
+            let path = temp_path(name);
+            scenario.save(&path).unwrap();
+            let loaded = Scenario::load(&path);
+            fs::remove_file(&path).unwrap();
+            let loaded = loaded.unwrap();
+            // Nothing is lost if the loaded scenario prints exactly the same
+            assert_eq!(
+                format!("{:?}", loaded),
+                format!("{:?}", scenario),
+                "through {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn the_example_scenarios_load() {
+        for name in ["hunt.ron", "two-flocks.toml"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("scenarios")
+                .join(name);
+            Scenario::load(&path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use comets::{
+    comet::{Comet, CometBehaviour, Gravity},
+    integrator::Integrator,
+    rules::{Filter, Rules},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, SamplingMode};
+use nannou::prelude::Vec2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// `n` comets scattered over a disc that grows with them, so they are as crowded at any size,
+/// each attracted to its nearest comets within 100
+fn comets(n: usize) -> Vec<Comet> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let radius = (n as f32).sqrt() * 10.0;
+    let behaviour = CometBehaviour {
+        rules: Rules {
+            filter: Filter::Distance {
+                min: 0.0,
+                max: 100.0,
+            },
+            ..Rules::default()
+        },
+        ..CometBehaviour::default()
+    };
+    (0..n)
+        .map(|_| {
+            let position = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * radius;
+            Comet::new(position, Vec2::ZERO, behaviour.clone(), &mut rng)
+        })
+        .collect()
+}
+
+/// Move every comet on by a step, as `update` does on every frame. Compare runs with and
+/// without `--features parallel` to see the speedup.
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for n in [1000, 5000, 20000] {
+        let comets = comets(n);
+        for (name, gravity) in [
+            ("local", Gravity::Local),
+            ("barnes_hut", Gravity::BarnesHut { theta: 0.5 }),
+        ] {
+            // Every comet checks every other in local gravity, which takes seconds a step at
+            // 20000 comets, so those steps are timed as few times as criterion allows
+            group.sampling_mode(if gravity == Gravity::Local && n > 5000 {
+                SamplingMode::Flat
+            } else {
+                SamplingMode::Auto
+            });
+            group.bench_with_input(BenchmarkId::new(name, n), &comets, |b, comets| {
+                b.iter_batched_ref(
+                    || comets.clone(),
+                    |comets| Integrator::SemiImplicitEuler.step(comets, gravity, 10.0, 1.0),
+                    criterion::BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
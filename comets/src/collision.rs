@@ -0,0 +1,193 @@
+use crate::comet::Comet;
+use nannou::geom::Point2;
+use std::fmt::Display;
+
+/// What happens when two comets overlap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Collisions {
+    /// The comets merge into one, conserving momentum, so the scene comes down to a few large
+    /// bodies over time
+    Merge,
+    /// The comets bounce off each other elastically
+    Bounce,
+}
+
+/// Two comets that merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Merge {
+    /// The index of the lighter comet, which was absorbed
+    pub absorbed: usize,
+    /// The index of the heavier comet, which absorbed it
+    pub into: usize,
+    /// The mass of the merged comet
+    pub mass: f32,
+    pub position: Point2,
+}
+
+impl Display for Merge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "comet {} merged into comet {} at {}, which now has mass {}",
+            self.absorbed, self.into, self.position, self.mass
+        )
+    }
+}
+
+impl Collisions {
+    /// Resolve the comets that overlap, returning the merges.
+    ///
+    /// A comet only collides once per call, so a comet that has just grown by merging doesn't
+    /// sweep up its neighbours in the same step. The indices of the merges are from before the
+    /// absorbed comets were removed.
+    pub fn resolve(self, comets: &mut Vec<Comet>) -> Vec<Merge> {
+        let mut collided = vec![false; comets.len()];
+        let mut merges = vec![];
+        for (i, j) in overlapping(comets) {
+            if collided[i] || collided[j] {
+                continue;
+            }
+            collided[i] = true;
+            collided[j] = true;
+            match self {
+                Collisions::Merge => {
+                    let (into, absorbed) = if comets[i].behaviour.mass >= comets[j].behaviour.mass {
+                        (i, j)
+                    } else {
+                        (j, i)
+                    };
+                    let other = comets[absorbed].clone();
+                    comets[into].merge(&other);
+                    merges.push(Merge {
+                        absorbed,
+                        into,
+                        mass: comets[into].behaviour.mass,
+                        position: comets[into].position,
+                    });
+                }
+                Collisions::Bounce => {
+                    let (left, right) = comets.split_at_mut(j);
+                    left[i].bounce(&mut right[0]);
+                }
+            }
+        }
+
+        if !merges.is_empty() {
+            let mut absorbed = vec![false; comets.len()];
+            for merge in &merges {
+                absorbed[merge.absorbed] = true;
+            }
+            let mut index = 0;
+            comets.retain(|_| {
+                index += 1;
+                !absorbed[index - 1]
+            });
+        }
+        merges
+    }
+}
+
+/// The pairs of comets that overlap, each as `(i, j)` with `i < j`, closest first.
+///
+/// The comets are swept from left to right, so that each is only checked against the comets
+/// whose horizontal extent overlaps its own.
+fn overlapping(comets: &[Comet]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..comets.len()).collect();
+    order.sort_by(|&a, &b| {
+        let left = |i: usize| comets[i].position.x - comets[i].radius();
+        left(a).total_cmp(&left(b))
+    });
+
+    let mut pairs = vec![];
+    for (n, &a) in order.iter().enumerate() {
+        let right = comets[a].position.x + comets[a].radius();
+        for &b in order[n + 1..]
+            .iter()
+            .take_while(|&&b| comets[b].position.x - comets[b].radius() <= right)
+        {
+            let distance = comets[a].position.distance(comets[b].position);
+            if distance < comets[a].radius() + comets[b].radius() {
+                pairs.push((distance, a.min(b), a.max(b)));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    pairs.into_iter().map(|(_, i, j)| (i, j)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comet::CometBehaviour;
+    use nannou::geom::{pt2, vec2, Vec2};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Two overlapping comets of different masses, heading into each other off-centre
+    fn colliding_pair() -> Vec<Comet> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut comet = |position, velocity, mass, width| {
+            let behaviour = CometBehaviour {
+                mass,
+                width,
+                ..CometBehaviour::default()
+            };
+            Comet::new(position, velocity, behaviour, &mut rng)
+        };
+        vec![
+            comet(pt2(0.0, 0.0), vec2(3.0, 1.0), 10.0, 8.0),
+            comet(pt2(5.0, 2.0), vec2(-2.0, 0.5), 25.0, 12.0),
+        ]
+    }
+
+    fn mass(comets: &[Comet]) -> f32 {
+        comets.iter().map(|comet| comet.behaviour.mass).sum()
+    }
+
+    fn momentum(comets: &[Comet]) -> Vec2 {
+        comets.iter().fold(Vec2::ZERO, |momentum, comet| {
+            momentum + comet.velocity * comet.behaviour.mass
+        })
+    }
+
+    fn kinetic_energy(comets: &[Comet]) -> f32 {
+        comets
+            .iter()
+            .map(|comet| 0.5 * comet.behaviour.mass * comet.velocity.length_squared())
+            .sum()
+    }
+
+    #[test]
+    fn merging_conserves_mass_and_momentum() {
+        let mut comets = colliding_pair();
+        let (mass_before, momentum_before) = (mass(&comets), momentum(&comets));
+
+        let merges = Collisions::Merge.resolve(&mut comets);
+
+        assert_eq!(merges.len(), 1);
+        assert_eq!((merges[0].absorbed, merges[0].into), (0, 1));
+        assert_eq!(comets.len(), 1);
+        assert_eq!(mass(&comets), mass_before);
+        assert!(momentum(&comets).distance(momentum_before) < 1e-4);
+    }
+
+    #[test]
+    fn bouncing_conserves_energy_and_momentum() {
+        let mut comets = colliding_pair();
+        let (energy_before, momentum_before) = (kinetic_energy(&comets), momentum(&comets));
+        let velocities_before: Vec<Vec2> = comets.iter().map(|comet| comet.velocity).collect();
+
+        let merges = Collisions::Bounce.resolve(&mut comets);
+
+        assert!(merges.is_empty());
+        assert_eq!(comets.len(), 2);
+        assert_ne!(
+            comets[0].velocity, velocities_before[0],
+            "they should bounce"
+        );
+        assert!((kinetic_energy(&comets) - energy_before).abs() < energy_before * 1e-5);
+        assert!(momentum(&comets).distance(momentum_before) < 1e-4);
+        // And they are pushed apart until they just touch
+        let gap = comets[0].position.distance(comets[1].position);
+        assert!((gap - comets[0].radius() - comets[1].radius()).abs() < 1e-4);
+    }
+}
@@ -1,21 +1,59 @@
 extern crate nannou;
-use std::fmt::Display;
+use std::{collections::VecDeque, fmt::Display};
 
 use nannou::{
-    draw,
     geom::{Point2, Vec2},
     prelude::*,
 };
+use rand::Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sketch::scene::Scene;
 
-#[derive(Debug, Clone, Copy)]
+use crate::{quadtree::QuadTree, rules::Rules};
+
+/// How much simulated time passes between the points of a trail, so that trails look the same
+/// at any frame rate
+const TRAIL_INTERVAL: f32 = 0.25;
+
+/// How a comet's trail is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Trail {
+    /// How much simulated time the trail covers (zero for no trail)
+    pub length: f32,
+    /// How much narrower the end of the trail is than the comet, from 0 (not at all) to 1 (it
+    /// tapers to nothing)
+    pub taper: f32,
+    /// How much more transparent the end of the trail is than the comet, from 0 (not at all)
+    /// to 1 (it fades out completely)
+    pub fade: f32,
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Trail {
+            length: 6.0,
+            taper: 1.0,
+            fade: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CometBehaviour {
     // What an comet is
     /// The color of the comet (used to draw it on the screen and to color the trail it leaves behind)
+    #[serde(with = "sketch::serialize::rgba")]
     pub color: Rgba<f32>,
     /// The width of the comet (Across its direction of travel)
     pub width: f32,
     /// The length of the comet (Along its direction of travel)
     pub length: f32,
+    /// The trail the comet leaves behind
+    pub trail: Trail,
 
     // How an comet behaves
     /// The central tendency force is a force that pulls the comet towards the center of the screen
@@ -32,21 +70,118 @@ pub struct CometBehaviour {
     // Technical
     /// The maximum number of comets that a comet can be attracted to
     pub max_local_comets: usize,
-    /// A filter function that determines which comets a comet can be attracted to
-    pub filter_fn: fn(this: &Comet, that: &Comet) -> bool,
-    /// A sort function that determines the order in which comets are considered for attraction
-    pub sort_fn: fn(this: &Comet, that: &Comet) -> i32,
+    /// Which comets a comet can be attracted to, and the order in which they are considered
+    pub rules: Rules,
     /// The group of the comet (used to determine which comets can be attracted to each other)
     pub group: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Default for CometBehaviour {
+    fn default() -> Self {
+        CometBehaviour {
+            color: rgba(0.8, 0.8, 0.8, 1.0),
+            width: 2.0,
+            length: 4.0,
+            trail: Trail::default(),
+
+            central_tendency: 25.0,
+            gravity: 7.0,
+            friction: 0.0,
+            mass: 10.0,
+            rotation: 0.0,
+
+            max_local_comets: 8,
+            rules: Rules::default(),
+            group: 0,
+        }
+    }
+}
+
+/// Which comets attract each other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gravity {
+    /// Each comet is attracted to the few comets chosen by its behaviour's `rules` and
+    /// `max_local_comets`
+    Local,
+    /// Every comet is attracted to every other, approximated with a Barnes–Hut quadtree with
+    /// opening angle `theta` (zero is exact)
+    BarnesHut { theta: f32 },
+}
+
+/// The attraction of each comet to the others (before gravity and its own mass are applied),
+/// with Plummer `softening`.
+///
+/// Each comet's attraction only depends on where the others are, so with the `parallel`
+/// feature they are worked out on every core, with the same results.
+pub fn attractions(comets: &[Comet], gravity: Gravity, softening: f32) -> Vec<Vec2> {
+    attractions_by(comets, gravity, softening, |count, f| {
+        for_each_comet(count, f)
+    })
+}
+
+/// The attractions worked out one comet after another, even with the `parallel` feature
+#[cfg(all(test, feature = "parallel"))]
+pub(crate) fn attractions_in_turn(comets: &[Comet], gravity: Gravity, softening: f32) -> Vec<Vec2> {
+    attractions_by(comets, gravity, softening, |count, f| {
+        (0..count).map(f).collect()
+    })
+}
+
+/// The attractions, with `each(count, f)` working out `f` of the index of each comet
+fn attractions_by(
+    comets: &[Comet],
+    gravity: Gravity,
+    softening: f32,
+    each: impl Fn(usize, &(dyn Fn(usize) -> Vec2 + Sync)) -> Vec<Vec2>,
+) -> Vec<Vec2> {
+    match gravity {
+        Gravity::Local => each(comets.len(), &|i| {
+            comets[i].local_attraction(comets, softening)
+        }),
+        Gravity::BarnesHut { theta } => {
+            let tree = QuadTree::new(comets);
+            each(comets.len(), &|i| tree.attraction(i, theta, softening))
+        }
+    }
+}
+
+/// `f` of the index of each of `count` comets, on every core with the `parallel` feature
+fn for_each_comet<T: Send>(count: usize, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
+    #[cfg(feature = "parallel")]
+    let results = (0..count).into_par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    let results = (0..count).map(f).collect();
+    results
+}
+
+/// The attraction towards a body of `mass` at `to` from `from`, which falls off with the
+/// distance between them.
+///
+/// Plummer `softening` measures the distance as `sqrt(distance² + softening²)`, so the
+/// attraction peaks at `mass / (2 * softening)` when they are `softening` apart and eases off
+/// to zero as they pass through each other, rather than growing without bound.
+pub(crate) fn attraction(from: Point2, to: Point2, mass: f32, softening: f32) -> Vec2 {
+    let offset = to - from;
+    let distance_squared = offset.length_squared() + softening * softening;
+    if distance_squared == 0.0 {
+        return Vec2::ZERO;
+    }
+    offset * mass / distance_squared
+}
+
+#[derive(Debug, Clone)]
 pub struct Comet {
     id: u32,
     pub position: Point2,
     last: Point2,
     pub velocity: Vec2,
     pub behaviour: CometBehaviour,
+    /// Where the comet was, newest first, `TRAIL_INTERVAL` apart in simulated time
+    trail: VecDeque<Point2>,
+    /// The simulated time since the newest point of the trail
+    trail_age: f32,
+    /// The simulated time since the comet was made
+    age: f32,
 }
 
 impl Display for Comet {
@@ -60,16 +195,38 @@ impl Display for Comet {
 }
 
 impl Comet {
-    pub fn new(position: Point2, velocity: Vec2, behaviour: CometBehaviour) -> Self {
+    pub fn new(
+        position: Point2,
+        velocity: Vec2,
+        behaviour: CometBehaviour,
+        rng: &mut impl Rng,
+    ) -> Self {
         Comet {
-            id: random_range(0, u32::MAX),
+            id: rng.gen(),
             position,
             velocity,
             behaviour,
             last: position,
+            trail: VecDeque::new(),
+            trail_age: 0.0,
+            age: 0.0,
+        }
+    }
+
+    /// How long the comet has existed for, in simulated time
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+
+    /// A copy of the comet without its trail, for trying out where it would move to
+    pub(crate) fn without_trail(&self) -> Comet {
+        Comet {
+            behaviour: self.behaviour.clone(),
+            trail: VecDeque::new(),
+            ..*self
         }
     }
-    pub fn draw(&self, draw: &draw::Draw) {
+    pub fn draw(&self, scene: &mut Scene) {
         // draw.ellipse()
         //     .x_y(self.position.x, self.position.y)
         //     .w_h(
@@ -80,45 +237,203 @@ impl Comet {
         //     )
         //     .rotate(-self.velocity.angle_between(vec2(1.0, 0.0)))
         //     .color(self.behaviour.color);
-        draw.line()
-            .start(self.last)
-            .end(self.position)
-            .stroke_weight(self.behaviour.width)
-            .caps_round()
-            .color(self.behaviour.color);
+        let Trail {
+            length,
+            taper,
+            fade,
+        } = self.behaviour.trail;
+        let color = self.behaviour.color;
+        let mut segment = |start: Point2, end: Point2, age: f32| {
+            let age = (age / length).clamp(0.0, 1.0);
+            scene
+                .line()
+                .start(start)
+                .end(end)
+                .stroke_weight(self.behaviour.width * (1.0 - taper * age))
+                .caps_round()
+                .color(rgba(
+                    color.red,
+                    color.green,
+                    color.blue,
+                    color.alpha * (1.0 - fade * age),
+                ));
+        };
+
+        // Oldest first, so newer parts of the trail are drawn over older ones
+        let ages = (0..self.trail.len()).map(|i| self.trail_age + i as f32 * TRAIL_INTERVAL);
+        let points: Vec<(Point2, f32)> = self.trail.iter().copied().zip(ages).collect();
+        for pair in points.windows(2).rev() {
+            let ((newer, newer_age), (older, older_age)) = (pair[0], pair[1]);
+            segment(older, newer, (newer_age + older_age) / 2.0);
+        }
+        if let Some(&(newest, age)) = points.first() {
+            segment(newest, self.position, age / 2.0);
+        }
+        segment(self.last, self.position, 0.0);
     }
 
-    pub fn update(&mut self, delta: f64, comets: &[Comet]) {
+    /// The comets that this comet's rules pick out of `comets`, in the order of its rules
+    pub fn local_comets<'a>(&self, comets: &'a [Comet]) -> Vec<&'a Comet> {
+        let rules = &self.behaviour.rules;
         let mut comets = comets
             .iter()
-            .filter(|comet| comet.id != self.id)
+            .filter(|comet| comet.id != self.id && rules.filter.allows(self, comet))
             .collect::<Vec<_>>();
-        comets.sort_by_key(|comet| (self.behaviour.sort_fn)(self, &comet));
-        let attraction_force = comets
+        comets.sort_by(|a, b| rules.sort.compare(self, a, b));
+        comets.truncate(self.behaviour.max_local_comets);
+        comets
+    }
+
+    /// The attraction of this comet to the comets its behaviour picks out of `comets`
+    pub fn local_attraction(&self, comets: &[Comet], softening: f32) -> Vec2 {
+        self.local_comets(comets)
             .iter()
-            .filter(|that| (self.behaviour.filter_fn)(self, that))
-            .take(self.behaviour.max_local_comets)
-            .filter(|comet| comet.id != self.id)
             .map(|comet| {
-                let distance = (comet.position - self.position).length();
-                let direction = (comet.position - self.position).normalize_or_zero();
-                direction * distance.recip().min(f32::MAX) * comet.behaviour.mass
+                attraction(
+                    self.position,
+                    comet.position,
+                    comet.behaviour.mass,
+                    softening,
+                )
             })
-            .fold(Vec2::new(0.0, 0.0), |acc, force| acc + force);
+            .fold(Vec2::new(0.0, 0.0), |acc, force| acc + force)
+    }
 
+    /// The comet's acceleration, given its `attraction_force` to the others
+    pub fn acceleration(&self, attraction_force: Vec2) -> Vec2 {
         let friction_force = self.velocity * -1.0;
-        let central_tendency_force = -self.position.normalize();
-        let rotation_force = self.position.perp().normalize() * self.position.length_recip();
-
-        self.velocity += (
-            // Calculate the acceleration
-            attraction_force * self.behaviour.gravity
-                + friction_force * self.behaviour.friction
-                + central_tendency_force * self.behaviour.central_tendency
-                + rotation_force * self.behaviour.rotation
-        ) / self.behaviour.mass
-            * delta as f32;
+        // Both are zero at the centre itself, where they have no direction
+        let central_tendency_force = -self.position.normalize_or_zero();
+        let rotation_force = if self.position == Vec2::ZERO {
+            Vec2::ZERO
+        } else {
+            self.position.perp() / self.position.length_squared()
+        };
+
+        (attraction_force * self.behaviour.gravity
+            + friction_force * self.behaviour.friction
+            + central_tendency_force * self.behaviour.central_tendency
+            + rotation_force * self.behaviour.rotation)
+            / self.behaviour.mass
+    }
+
+    /// How far from its position another comet has to come to collide with it
+    pub fn radius(&self) -> f32 {
+        self.behaviour.width / 2.0
+    }
+
+    /// Absorb `other` into this comet, conserving momentum: the masses are summed, the comet
+    /// moves to their centre of mass, and its colour is blended by mass. The merged comet is as
+    /// wide (and long) as both of them put together, by area.
+    pub fn merge(&mut self, other: &Comet) {
+        let (mass, other_mass) = (self.behaviour.mass, other.behaviour.mass);
+        let total = mass + other_mass;
+        let blend = |a: f32, b: f32| (a * mass + b * other_mass) / total;
+
+        self.position = Vec2::new(
+            blend(self.position.x, other.position.x),
+            blend(self.position.y, other.position.y),
+        );
+        self.last = self.position;
+        self.velocity = (self.velocity * mass + other.velocity * other_mass) / total;
+
+        let (color, other_color) = (self.behaviour.color, other.behaviour.color);
+        self.behaviour.color = rgba(
+            blend(color.red, other_color.red),
+            blend(color.green, other_color.green),
+            blend(color.blue, other_color.blue),
+            blend(color.alpha, other_color.alpha),
+        );
+        self.behaviour.width = self.behaviour.width.hypot(other.behaviour.width);
+        self.behaviour.length = self.behaviour.length.hypot(other.behaviour.length);
+        self.behaviour.mass = total;
+    }
+
+    /// Bounce this comet and `other` off each other elastically, and push them apart so they no
+    /// longer overlap
+    pub fn bounce(&mut self, other: &mut Comet) {
+        let (mass, other_mass) = (self.behaviour.mass, other.behaviour.mass);
+        let total = mass + other_mass;
+        let offset = other.position - self.position;
+        let distance = offset.length();
+        // Comets in exactly the same place are pushed apart sideways
+        let normal = if distance > 0.0 {
+            offset / distance
+        } else {
+            Vec2::X
+        };
+
+        let closing_speed = (self.velocity - other.velocity).dot(normal);
+        if closing_speed > 0.0 {
+            let impulse = 2.0 * mass * other_mass / total * closing_speed;
+            self.velocity -= normal * impulse / mass;
+            other.velocity += normal * impulse / other_mass;
+        }
+
+        let overlap = (self.radius() + other.radius() - distance).max(0.0);
+        self.position -= normal * overlap * other_mass / total;
+        other.position += normal * overlap * mass / total;
+    }
+
+    /// Age the comet after it has moved on by `delta` time steps, adding to its trail and
+    /// dropping the points that have grown older than the trail is long
+    pub fn grow_older(&mut self, delta: f32) {
+        self.age += delta;
+        self.trail_age += delta;
+        while self.trail_age >= TRAIL_INTERVAL {
+            self.trail_age -= TRAIL_INTERVAL;
+            // The point is placed where the comet was when it was due, part way through the
+            // step
+            let back = if delta > 0.0 {
+                (self.trail_age / delta).min(1.0)
+            } else {
+                0.0
+            };
+            self.trail.push_front(self.position.lerp(self.last, back));
+        }
+        let points = (self.behaviour.trail.length / TRAIL_INTERVAL).ceil() as usize;
+        self.trail.truncate(points);
+    }
+
+    /// Move the comet to `position`, remembering where it was to draw its trail
+    pub fn move_to(&mut self, position: Point2, velocity: Vec2) {
         self.last = self.position;
-        self.position += self.velocity * delta as f32;
+        self.position = position;
+        self.velocity = velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Each comet's attraction is worked out on its own, so working them all out at once (in
+    /// parallel or not) gives exactly what working each out alone does
+    #[test]
+    fn attractions_match_each_comet_alone() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let comets: Vec<Comet> = (0..500)
+            .map(|_| {
+                let position =
+                    Vec2::new(rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+                Comet::new(position, Vec2::ZERO, CometBehaviour::default(), &mut rng)
+            })
+            .collect();
+
+        let alone: Vec<Vec2> = comets
+            .iter()
+            .map(|comet| comet.local_attraction(&comets, 10.0))
+            .collect();
+        assert_eq!(attractions(&comets, Gravity::Local, 10.0), alone);
+
+        let tree = QuadTree::new(&comets);
+        let alone: Vec<Vec2> = (0..comets.len())
+            .map(|i| tree.attraction(i, 0.5, 10.0))
+            .collect();
+        assert_eq!(
+            attractions(&comets, Gravity::BarnesHut { theta: 0.5 }, 10.0),
+            alone
+        );
     }
 }
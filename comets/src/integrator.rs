@@ -0,0 +1,344 @@
+use crate::comet::{attractions, Comet, Gravity};
+use nannou::geom::{Point2, Vec2};
+use std::fmt::Display;
+
+/// Works out the attraction of each comet to the others, as `comet::attractions` does
+type Attractions = fn(&[Comet], Gravity, f32) -> Vec<Vec2>;
+
+/// How the comets are moved on by a step, given their accelerations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Integrator {
+    /// Explicit (forward) Euler: moves with the velocity from before the step. Orbits gain
+    /// energy and spiral out.
+    Euler,
+    /// Semi-implicit (symplectic) Euler: updates the velocity first and moves with that. Cheap,
+    /// and orbits stay bounded.
+    SemiImplicitEuler,
+    /// Velocity Verlet (leapfrog): second order and symplectic, for two evaluations of the
+    /// forces per step.
+    VelocityVerlet,
+    /// Classic fourth order Runge–Kutta: the most accurate over a step, for four evaluations of
+    /// the forces, but energy still drifts slowly over many steps.
+    Rk4,
+}
+
+impl Integrator {
+    /// Move the comets on by `delta` time steps, attracting each other with `gravity` softened
+    /// by `softening`.
+    ///
+    /// If any comet's position or velocity would stop being finite, none of them are moved and
+    /// the first such comet is returned as an error.
+    pub fn step(
+        self,
+        comets: &mut [Comet],
+        gravity: Gravity,
+        softening: f32,
+        delta: f32,
+    ) -> Result<(), Diverged> {
+        self.step_by(comets, gravity, softening, delta, attractions)
+    }
+
+    /// Move the comets on by a step as `step` does, working out their attractions with
+    /// `attractions`
+    fn step_by(
+        self,
+        comets: &mut [Comet],
+        gravity: Gravity,
+        softening: f32,
+        delta: f32,
+        attractions: Attractions,
+    ) -> Result<(), Diverged> {
+        let states: Vec<(Point2, Vec2)> = match self {
+            Integrator::Euler => comets
+                .iter()
+                .zip(accelerations(comets, gravity, softening, attractions))
+                .map(|(comet, a)| {
+                    (
+                        comet.position + comet.velocity * delta,
+                        comet.velocity + a * delta,
+                    )
+                })
+                .collect(),
+            Integrator::SemiImplicitEuler => comets
+                .iter()
+                .zip(accelerations(comets, gravity, softening, attractions))
+                .map(|(comet, a)| {
+                    let velocity = comet.velocity + a * delta;
+                    (comet.position + velocity * delta, velocity)
+                })
+                .collect(),
+            Integrator::VelocityVerlet => {
+                // Friction depends on the velocity, so the second evaluation of the forces uses
+                // the velocity half way through the step
+                let moved: Vec<Comet> = comets
+                    .iter()
+                    .zip(accelerations(comets, gravity, softening, attractions))
+                    .map(|(comet, a)| {
+                        let velocity = comet.velocity + a * delta / 2.0;
+                        let mut comet = comet.without_trail();
+                        comet.move_to(comet.position + velocity * delta, velocity);
+                        comet
+                    })
+                    .collect();
+                moved
+                    .iter()
+                    .zip(accelerations(&moved, gravity, softening, attractions))
+                    .map(|(comet, a)| (comet.position, comet.velocity + a * delta / 2.0))
+                    .collect()
+            }
+            Integrator::Rk4 => {
+                let k1 = rates(comets, gravity, softening, attractions);
+                let k2 = rates(
+                    &offset(comets, &k1, delta / 2.0),
+                    gravity,
+                    softening,
+                    attractions,
+                );
+                let k3 = rates(
+                    &offset(comets, &k2, delta / 2.0),
+                    gravity,
+                    softening,
+                    attractions,
+                );
+                let k4 = rates(&offset(comets, &k3, delta), gravity, softening, attractions);
+                comets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, comet)| {
+                        let (dx, dv) = [(k1[i], 1.0), (k2[i], 2.0), (k3[i], 2.0), (k4[i], 1.0)]
+                            .iter()
+                            .fold((Vec2::ZERO, Vec2::ZERO), |(dx, dv), &((x, v), weight)| {
+                                (dx + x * weight, dv + v * weight)
+                            });
+                        (
+                            comet.position + dx * delta / 6.0,
+                            comet.velocity + dv * delta / 6.0,
+                        )
+                    })
+                    .collect()
+            }
+        };
+        if let Some(index) = states
+            .iter()
+            .position(|(position, velocity)| !position.is_finite() || !velocity.is_finite())
+        {
+            return Err(Diverged {
+                index,
+                position: comets[index].position,
+                velocity: comets[index].velocity,
+            });
+        }
+        for (comet, (position, velocity)) in comets.iter_mut().zip(states) {
+            comet.move_to(position, velocity);
+        }
+        Ok(())
+    }
+}
+
+/// A comet whose position or velocity stopped being finite during a step
+#[derive(Debug, Clone)]
+pub struct Diverged {
+    /// The index of the comet
+    pub index: usize,
+    /// Where the comet was before the step
+    pub position: Point2,
+    /// How fast the comet was moving before the step
+    pub velocity: Vec2,
+}
+
+impl Display for Diverged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "comet {} diverged (its position or velocity is no longer finite) from position {} \
+             with velocity {}",
+            self.index, self.position, self.velocity
+        )
+    }
+}
+
+impl std::error::Error for Diverged {}
+
+/// The acceleration of each comet
+fn accelerations(
+    comets: &[Comet],
+    gravity: Gravity,
+    softening: f32,
+    attractions: Attractions,
+) -> Vec<Vec2> {
+    comets
+        .iter()
+        .zip(attractions(comets, gravity, softening))
+        .map(|(comet, attraction)| comet.acceleration(attraction))
+        .collect()
+}
+
+/// How fast the position and velocity of each comet are changing
+fn rates(
+    comets: &[Comet],
+    gravity: Gravity,
+    softening: f32,
+    attractions: Attractions,
+) -> Vec<(Vec2, Vec2)> {
+    comets
+        .iter()
+        .zip(accelerations(comets, gravity, softening, attractions))
+        .map(|(comet, a)| (comet.velocity, a))
+        .collect()
+}
+
+/// Copies of the comets with their positions and velocities moved on at `rates` for `dt`
+fn offset(comets: &[Comet], rates: &[(Vec2, Vec2)], dt: f32) -> Vec<Comet> {
+    comets
+        .iter()
+        .zip(rates)
+        .map(|(comet, &(velocity, acceleration))| {
+            let mut comet = comet.without_trail();
+            comet.move_to(
+                comet.position + velocity * dt,
+                comet.velocity + acceleration * dt,
+            );
+            comet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{comet::CometBehaviour, invariants::Invariants};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const SOFTENING: f32 = 10.0;
+
+    /// Two comets falling straight at each other, attracted only to each other
+    fn head_on(rng: &mut StdRng) -> Vec<Comet> {
+        let behaviour = CometBehaviour {
+            central_tendency: 0.0,
+            ..CometBehaviour::default()
+        };
+        vec![
+            Comet::new(
+                Vec2::new(-200.0, 0.0),
+                Vec2::new(2.0, 0.0),
+                behaviour.clone(),
+                rng,
+            ),
+            Comet::new(Vec2::new(200.0, 0.0), Vec2::new(-2.0, 0.0), behaviour, rng),
+        ]
+    }
+
+    #[test]
+    fn comets_pass_through_each_other() {
+        for integrator in [
+            Integrator::Euler,
+            Integrator::SemiImplicitEuler,
+            Integrator::VelocityVerlet,
+            Integrator::Rk4,
+        ] {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut comets = head_on(&mut rng);
+            let start = Invariants::measure(&comets, SOFTENING);
+            let mut passed = false;
+            for _ in 0..2000 {
+                integrator
+                    .step(
+                        &mut comets,
+                        Gravity::BarnesHut { theta: 0.0 },
+                        SOFTENING,
+                        0.1,
+                    )
+                    .unwrap_or_else(|e| panic!("{:?}: {}", integrator, e));
+                passed |= comets[0].position.x > comets[1].position.x;
+                for comet in &comets {
+                    assert!(
+                        comet.velocity.length() < 10.0,
+                        "{:?}: {} exploded",
+                        integrator,
+                        comet
+                    );
+                }
+            }
+            assert!(passed, "{:?}: the comets never passed", integrator);
+
+            if integrator != Integrator::Euler {
+                let now = Invariants::measure(&comets, SOFTENING);
+                let drift = (now.energy() - start.energy()) / start.energy().abs();
+                assert!(
+                    drift.abs() < 0.01,
+                    "{:?}: energy drifted by {}",
+                    integrator,
+                    drift
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reports_the_comet_that_diverged() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut comets = head_on(&mut rng);
+        comets[1].velocity = Vec2::new(f32::MAX, 0.0);
+        let before = comets.clone();
+        let error = Integrator::SemiImplicitEuler
+            .step(&mut comets, Gravity::Local, SOFTENING, 10.0)
+            .unwrap_err();
+        assert_eq!(error.index, 1);
+        assert_eq!(comets[0].position, before[0].position);
+    }
+
+    /// Working out the attractions on every core moves the comets bit for bit as working them
+    /// out one after another does, with every integrator and both kinds of gravity
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn stepping_in_parallel_matches_stepping_in_turn() {
+        use crate::comet::attractions_in_turn;
+        use rand::Rng;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let start: Vec<Comet> = (0..100)
+            .map(|_| {
+                let position =
+                    Vec2::new(rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+                let velocity = Vec2::new(rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+                Comet::new(position, velocity, CometBehaviour::default(), &mut rng)
+            })
+            .collect();
+        let state = |comets: &[Comet]| -> Vec<[u32; 4]> {
+            comets
+                .iter()
+                .map(|comet| {
+                    let (position, velocity) = (comet.position, comet.velocity);
+                    [position.x, position.y, velocity.x, velocity.y].map(f32::to_bits)
+                })
+                .collect()
+        };
+
+        for integrator in [
+            Integrator::Euler,
+            Integrator::SemiImplicitEuler,
+            Integrator::VelocityVerlet,
+            Integrator::Rk4,
+        ] {
+            for gravity in [Gravity::Local, Gravity::BarnesHut { theta: 0.5 }] {
+                let (mut parallel, mut in_turn) = (start.clone(), start.clone());
+                for step in 0..10 {
+                    integrator
+                        .step_by(&mut parallel, gravity, SOFTENING, 0.5, attractions)
+                        .unwrap();
+                    integrator
+                        .step_by(&mut in_turn, gravity, SOFTENING, 0.5, attractions_in_turn)
+                        .unwrap();
+                    assert_eq!(
+                        state(&parallel),
+                        state(&in_turn),
+                        "{:?} with {:?} on step {}",
+                        integrator,
+                        gravity,
+                        step
+                    );
+                }
+            }
+        }
+    }
+}
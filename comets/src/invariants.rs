@@ -0,0 +1,56 @@
+use crate::comet::Comet;
+
+/// The energy and angular momentum of the comets, which a perfect integrator would keep
+/// constant, so that the drift of different integrators can be compared.
+///
+/// Only gravity between comets and the central tendency are conservative: rotation and friction
+/// add or take away energy and angular momentum by design, and in `Gravity::Local` each comet
+/// feels only some of the others, so the totals only stay constant without them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Invariants {
+    pub kinetic_energy: f64,
+    /// The potential energy of gravity between every pair of comets and of the central tendency
+    pub potential_energy: f64,
+    /// Around the centre of the screen, anticlockwise
+    pub angular_momentum: f64,
+}
+
+impl Invariants {
+    /// Measure the comets, comparing every pair of them (O(n²)), for gravity with Plummer
+    /// `softening`
+    pub fn measure(comets: &[Comet], softening: f32) -> Self {
+        let mut kinetic_energy = 0.0;
+        let mut potential_energy = 0.0;
+        let mut angular_momentum = 0.0;
+        for (i, comet) in comets.iter().enumerate() {
+            let mass = comet.behaviour.mass as f64;
+            kinetic_energy += 0.5 * mass * comet.velocity.length_squared() as f64;
+            angular_momentum += mass * comet.position.perp_dot(comet.velocity) as f64;
+            potential_energy +=
+                comet.behaviour.central_tendency as f64 * comet.position.length() as f64;
+
+            // The attraction of a comet to another falls off with their (softened) distance, so
+            // its potential grows with the log of the distance
+            for other in &comets[i + 1..] {
+                let distance_squared = (comet.position.distance_squared(other.position)
+                    + softening * softening) as f64;
+                if distance_squared > 0.0 {
+                    let strength = (comet.behaviour.gravity * other.behaviour.mass
+                        + other.behaviour.gravity * comet.behaviour.mass)
+                        as f64
+                        / 2.0;
+                    potential_energy += strength * distance_squared.ln() / 2.0;
+                }
+            }
+        }
+        Invariants {
+            kinetic_energy,
+            potential_energy,
+            angular_momentum,
+        }
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+}
@@ -0,0 +1,8 @@
+pub mod collision;
+pub mod comet;
+pub mod integrator;
+pub mod invariants;
+pub mod prediction;
+pub mod preset;
+pub mod quadtree;
+pub mod rules;
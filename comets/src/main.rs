@@ -1,47 +1,123 @@
 extern crate nannou;
-mod comet;
 
+use clap::{Parser, ValueEnum};
+use comets::{
+    collision::Collisions,
+    comet::{self, Gravity},
+    integrator::Integrator,
+    invariants::Invariants,
+    prediction::Forecast,
+    preset::Preset,
+    rules::RulesConfig,
+};
 use nannou::prelude::*;
+use rand::rngs::StdRng;
+use sketch::config::{self, ConfigError};
+use sketch::{
+    plot::Plot,
+    record::{self, RecordArgs, Recorder},
+    scene::Scene,
+    seed::SeedArgs,
+    Sketch,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 const SPEED: u32 = 10;
 const TIME_STEP: f32 = 1.0 / SPEED as f32;
-const COMET_COUNT: u32 = 128;
-const TRAIL_FADE: f32 = 0.01;
-const COMET_BEHAVIOUR: comet::CometBehaviour = comet::CometBehaviour {
-    color: nannou::color::Alpha {
-        color: Rgb {
-            red: 0.8,
-            green: 0.8,
-            blue: 0.8,
-            standard: std::marker::PhantomData,
+/// How fast a launched comet goes for each pixel the mouse is dragged
+const LAUNCH_SPEED: f32 = 0.05;
+/// How many time steps ahead the path of a comet being launched is predicted
+const LAUNCH_PREVIEW_STEPS: usize = 100;
+/// How long each frame may spend forecasting, so that a long forecast is spread over several
+/// frames rather than freezing the sketch
+const FORECAST_BUDGET: Duration = Duration::from_millis(8);
+/// How close the mouse has to be to a comet to grab it
+const GRAB_DISTANCE: f32 = 20.0;
+
+/// How long the dashes of predicted paths are
+const DASH: f32 = 4.0;
+/// How many steps of drift in the energy and angular momentum are plotted
+const DRIFT_SAMPLES: usize = 300;
+
+/// A comet launched with the mouse
+fn launched_behaviour() -> comet::CometBehaviour {
+    comet::CometBehaviour {
+        color: rgba(1.0, 1.0, 0.9, 1.0),
+        ..Default::default()
+    }
+}
+
+/// A heavy comet placed with the mouse, to pull the others around
+fn attractor_behaviour() -> comet::CometBehaviour {
+    comet::CometBehaviour {
+        color: rgba(1.0, 0.5, 0.2, 1.0),
+        width: 8.0,
+        length: 8.0,
+        trail: comet::Trail {
+            length: 20.0,
+            taper: 0.5,
+            fade: 1.0,
         },
-        alpha: 1.0,
-    },
-
-    // forces
-    central_tendency: 25.0,
-    gravity: 7.0,
-    friction: 0.0,
-    mass: 10.0,
-    rotation: 0.0,
-
-    // comet
-    width: 2.0,
-    length: 4.0,
-
-    // technical
-    max_local_comets: 8,
-    group: 0,
-    sort_fn: |this: &comet::Comet, that: &comet::Comet| {
-        this.position.distance(that.position) as i32
-    },
-    filter_fn: |this: &comet::Comet, that: &comet::Comet| {
-        100.0 < this.position.distance(that.position)
-            && this.position.distance(that.position) < 250.0
-            && this.behaviour.group < that.behaviour.group
-            && this.behaviour.group%2 == that.behaviour.group%2
-    },
-};
+        mass: 2000.0,
+        group: 8,
+        ..Default::default()
+    }
+}
+
+/// Comets swirling around each other
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+    /// The preset to start with: the name of a built-in one, or a .toml or .ron file of another
+    /// (number keys switch between them)
+    #[arg(long, value_name = "PRESET", default_value = "swirl")]
+    preset: String,
+    /// Attract every comet to every other with a Barnes–Hut quadtree, opening cells wider than
+    /// THETA times their distance (0 is exact, around 0.5 is usual), instead of each comet's few
+    /// local comets
+    #[arg(long, value_name = "THETA")]
+    barnes_hut: Option<f32>,
+    /// How far apart two comets are when their attraction peaks, easing off to nothing as they
+    /// pass through each other (Plummer softening)
+    #[arg(long, value_name = "EPSILON", default_value_t = 10.0)]
+    softening: f32,
+    /// A .toml or .ron file of rules for which comets attract the comets of each group
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+    /// What happens when comets overlap (by default they pass through each other)
+    #[arg(long, value_enum)]
+    collisions: Option<Collisions>,
+    /// How the comets are moved on each step
+    #[arg(long, value_enum, default_value_t = Integrator::SemiImplicitEuler)]
+    integrator: Integrator,
+    /// Show the energy and angular momentum of the comets, and how far they have drifted since
+    /// the start (toggle with E)
+    #[arg(long)]
+    energy: bool,
+    /// Show where each comet is going, predicted by running the simulation ahead (toggle with P)
+    #[arg(long)]
+    predict: bool,
+    /// How many time steps ahead to predict
+    #[arg(long, value_name = "STEPS", default_value_t = 60)]
+    predict_steps: usize,
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
 fn main() {
+    let args = Args::parse();
+    if args.record.headless {
+        if let Err(e) = record::run_headless(&mut Model::new(&args), &args.record) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     nannou::app(model)
         .update(update)
         .event(event)
@@ -49,181 +125,622 @@ fn main() {
         .run();
 }
 
+/// A comet being launched: dragging the mouse away from it sets its velocity
+struct Launch {
+    comet: comet::Comet,
+    /// Where the other comets are going, while the simulation is paused
+    forecast: Forecast,
+    /// The predicted path of the comet
+    path: Vec<Point2>,
+}
+
+impl Launch {
+    /// Aim the comet at `mouse`, and predict its path
+    fn aim(&mut self, mouse: Point2) {
+        self.comet.velocity = (mouse - self.comet.position) * LAUNCH_SPEED;
+        self.forecast.run(LAUNCH_PREVIEW_STEPS, FORECAST_BUDGET);
+        self.path = self.forecast.path_of(&self.comet);
+    }
+}
+
+/// A force on every comet that can be made stronger or weaker while the sketch runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Force {
+    CentralTendency,
+    Rotation,
+    Gravity,
+}
+
+impl Force {
+    const ALL: [Force; 3] = [Force::CentralTendency, Force::Rotation, Force::Gravity];
+
+    fn name(self) -> &'static str {
+        match self {
+            Force::CentralTendency => "central tendency",
+            Force::Rotation => "rotation",
+            Force::Gravity => "gravity",
+        }
+    }
+
+    /// The strength of the force in `behaviour`
+    fn strength(self, behaviour: &mut comet::CometBehaviour) -> &mut f32 {
+        match self {
+            Force::CentralTendency => &mut behaviour.central_tendency,
+            Force::Rotation => &mut behaviour.rotation,
+            Force::Gravity => &mut behaviour.gravity,
+        }
+    }
+}
+
+/// A forecast of where the comets are going
+struct Prediction {
+    forecast: Forecast,
+    /// The simulated time when the forecast started
+    start: f32,
+}
+
 struct Model {
     comets: Vec<comet::Comet>,
+    presets: Vec<Preset>,
+    /// The index of the preset that was last started
+    preset: usize,
+    /// The `--rules`, to apply to each preset
+    rules: Option<RulesConfig>,
+    rng: StdRng,
+    gravity: Gravity,
+    softening: f32,
+    integrator: Integrator,
+    collisions: Option<Collisions>,
+    /// Set once a comet has diverged, which stops the simulation
+    diverged: bool,
+    show_invariants: bool,
+    show_rules: bool,
+    /// Where the mouse is, to pick the comet whose rules are shown
+    mouse: Point2,
+    /// The comet being launched by dragging the mouse
+    launch: Option<Launch>,
+    /// The index of the comet being moved with the mouse
+    grabbed: Option<usize>,
+    /// How many time steps have been simulated
+    time: f32,
+    show_prediction: bool,
+    prediction_steps: usize,
+    /// The last finished prediction, which is drawn
+    prediction: Option<Prediction>,
+    /// The prediction being worked on, a little each frame, to replace it
+    next_prediction: Option<Prediction>,
+    /// The force that the arrow keys change
+    tuned: Force,
+    /// How much each force has been scaled by since the preset started, in the order of
+    /// `Force::ALL`
+    scales: [f32; 3],
+    /// The energy and angular momentum at the start
+    start: Invariants,
+    /// The energy and angular momentum after the last step, measured while they are shown
+    now: Invariants,
+    /// How far the energy and angular momentum have drifted since the start, in percent
+    drift: Plot,
+    recorder: Recorder,
 }
 
-fn model(_app: &App) -> Model {
-    // Create the comets
-    let mut comets: Vec<comet::Comet> = vec![];
-    for i in 0..COMET_COUNT {
-        comets.push(comet::Comet::new(
-            Point2::new(1.0, 0.0).rotate(PI * 2.0 * (i as f32 / COMET_COUNT as f32)) * 100.0,
-            Vec2::new(0.0, 1.0).rotate(PI * 2.0 * (i as f32 / COMET_COUNT as f32)) * 3.0,
-            comet::CometBehaviour {
-                central_tendency: 25.0,
-                rotation: 50.0,
-                mass: 20.0,
-
-                color: nannou::color::Alpha {
-                    color: Rgb {
-                        red: 0.7,
-                        green: 0.0,
-                        blue: 0.7,
-                        standard: std::marker::PhantomData,
-                    },
-                    alpha: 0.7,
-                },
-
-                ..COMET_BEHAVIOUR
-            },
-        ));
-        comets.push(comet::Comet::new(
-            Point2::new(1.0, 0.0).rotate(PI * 2.0 * ((i as f32 + 0.5)  / COMET_COUNT as f32)) * 100.0,
-            Vec2::new(0.0, 1.0).rotate(PI * 2.0 * ((i as f32 + 0.5)  / COMET_COUNT as f32)) * 3.0,
-            comet::CometBehaviour {
-                central_tendency: 25.0,
-                rotation: 50.0,
-                mass: 20.0,
-
-                color: nannou::color::Alpha {
-                    color: Rgb {
-                        red: 0.0,
-                        green: 0.7,
-                        blue: 0.0,
-                        standard: std::marker::PhantomData,
-                    },
-                    alpha: 0.7,
-                },
-
-                ..COMET_BEHAVIOUR
-            },
-        ));
-        comets.push(comet::Comet::new(
-            Point2::new(1.0, 0.0).rotate(PI * 2.0 * ((i as f32 + 0.25)  / COMET_COUNT as f32)) * 100.0,
-            Vec2::new(0.0, 1.0).rotate(PI * 2.0 * ((i as f32 + 0.25)  / COMET_COUNT as f32)) * 3.0,
-            comet::CometBehaviour {
-                central_tendency: 25.0,
-                rotation: -50.0,
-                mass: 20.0,
-                color: nannou::color::Alpha {
-                    color: Rgb {
-                        red: 0.0,
-                        green: 0.7,
-                        blue: 0.7,
-                        standard: std::marker::PhantomData,
-                    },
-                    alpha: 0.7,
-                },
-                
-
-                ..COMET_BEHAVIOUR
-            },
-        ));
-        comets.push(comet::Comet::new(
-            Point2::new(1.0, 0.0).rotate(PI * 2.0 * ((i as f32 + 0.75)  / COMET_COUNT as f32)) * 100.0,
-            Vec2::new(0.0, 1.0).rotate(PI * 2.0 * ((i as f32 + 0.75)  / COMET_COUNT as f32)) * 3.0,
-            comet::CometBehaviour {
-                central_tendency: 25.0,
-                rotation: -50.0,
-                mass: 20.0,
-
-                color: nannou::color::Alpha {
-                    color: Rgb {
-                        red: 0.7,
-                        green: 0.7,
-                        blue: 0.7,
-                        standard: std::marker::PhantomData,
-                    },
-                    alpha: 0.7,
-                },
-
-                ..COMET_BEHAVIOUR
-            },
-        ));
-    }
-    for i in 0..3 {
-        comets.push(comet::Comet::new(
-            Point2::new(1.0, 0.0).rotate(PI * 2.0 * (i as f32 / 3.0)) * 200.0,
-            Vec2::new(0.0, 1.0).rotate(PI * 2.0 * (i as f32 / 3.0))*5.0,
-            comet::CometBehaviour {
-                central_tendency: 500.0,
-                rotation: 800.0,
-                mass: 4000.0,
-                friction: 20.0,
-
-                color: nannou::color::Alpha {
-                    color: Rgb {
-                        red: 0.0,
-                        green: 0.6,
-                        blue: 0.6,
-                        standard: std::marker::PhantomData,
-                    },
-                    alpha: 0.7,
-                },
-                group: 8,
-
-                ..COMET_BEHAVIOUR
+impl Model {
+    fn new(args: &Args) -> Self {
+        let exit = |e: ConfigError| -> ! {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        };
+        let mut presets = Preset::built_in();
+        let preset = match presets.iter().position(|preset| preset.name == args.preset) {
+            Some(index) => index,
+            None => {
+                presets.push(config::load(Path::new(&args.preset)).unwrap_or_else(|e| exit(e)));
+                presets.len() - 1
+            }
+        };
+        let rules = args
+            .rules
+            .as_ref()
+            .map(|path| config::load(path).unwrap_or_else(|e| exit(e)));
+
+        let mut model = Model {
+            comets: vec![],
+            presets,
+            preset,
+            rules,
+            rng: args.seed.rng(),
+            softening: args.softening,
+            integrator: args.integrator,
+            collisions: args.collisions,
+            diverged: false,
+            show_rules: false,
+            mouse: Point2::ZERO,
+            launch: None,
+            grabbed: None,
+            time: 0.0,
+            show_prediction: args.predict,
+            prediction_steps: args.predict_steps,
+            prediction: None,
+            next_prediction: None,
+            tuned: Force::Gravity,
+            scales: [1.0; 3],
+            show_invariants: args.energy,
+            gravity: match args.barnes_hut {
+                Some(theta) => Gravity::BarnesHut { theta },
+                None => Gravity::Local,
             },
-        ));
-        // comets.push(comet::Comet::new(
-        //     Point2::new(1.0, 0.0).rotate(PI * 2.0 * (i as f32 / 3.0)) * 200.0,
-        //     Vec2::new(0.0, -1.0).rotate(PI * 2.0 * (i as f32 / 3.0))*2.0,
-        //     comet::CometBehaviour {
-        //         central_tendency: 30.0,
-        //         rotation: -1500.0,
-        //         mass: 3000.0,
-        //         friction: 1.0,
+            start: Invariants::measure(&[], args.softening),
+            now: Invariants::measure(&[], args.softening),
+            drift: Plot::new(DRIFT_SAMPLES)
+                .series("energy", ORANGE)
+                .series("angular momentum", CORNFLOWERBLUE),
+            recorder: Recorder::default(),
+        };
+        model.reset(preset);
+        model
+    }
 
-        //         color: nannou::color::Alpha {
-        //             color: Rgb {
-        //                 red: 0.0,
-        //                 green: 0.6,
-        //                 blue: 0.6,
-        //                 standard: std::marker::PhantomData,
-        //             },
-        //             alpha: 1.0,
-        //         },
-        //         group: 8,
+    /// Start again from the preset at `index`, with the `--rules` applied
+    fn reset(&mut self, index: usize) {
+        let Some(preset) = self.presets.get(index) else {
+            return;
+        };
+        println!("preset {}: {}", index + 1, preset.name);
+        self.preset = index;
+        self.comets = preset.spawn(&mut self.rng);
+        if let Some(rules) = &self.rules {
+            rules.apply(&mut self.comets);
+        }
+        self.restart_invariants();
+        self.diverged = false;
+        self.grabbed = None;
+        self.scales = [1.0; 3];
+        self.forget_prediction();
+    }
 
-        //         ..COMET_BEHAVIOUR
-        //     },
-        // ));
+    /// A new comet at `position`, with the `--rules` for its group
+    fn new_comet(
+        &mut self,
+        position: Point2,
+        velocity: Vec2,
+        behaviour: comet::CometBehaviour,
+    ) -> comet::Comet {
+        let mut comet = comet::Comet::new(position, velocity, behaviour, &mut self.rng);
+        if let Some(rules) = &self.rules {
+            rules.apply(std::slice::from_mut(&mut comet));
+        }
+        for (force, scale) in Force::ALL.into_iter().zip(self.scales) {
+            *force.strength(&mut comet.behaviour) *= scale;
+        }
+        comet
+    }
+
+    /// Add `comet` to the simulation, measuring the energy and angular momentum again to compare
+    /// the drift with
+    fn add(&mut self, comet: comet::Comet) {
+        self.comets.push(comet);
+        self.restart_invariants();
+        self.forget_prediction();
+    }
+
+    /// Measure the energy and angular momentum to compare the drift with from now on
+    fn restart_invariants(&mut self) {
+        self.start = Invariants::measure(&self.comets, self.softening);
+        self.now = self.start;
+        self.drift.clear();
+    }
+
+    /// Show or hide the energy and angular momentum, plotting their drift afresh when shown, as
+    /// they aren't measured while hidden
+    fn toggle_invariants(&mut self) {
+        self.show_invariants = !self.show_invariants;
+        if self.show_invariants {
+            self.now = Invariants::measure(&self.comets, self.softening);
+            self.drift.clear();
+        }
+    }
+
+    /// Measure the energy and angular momentum after a step, and plot how far they have drifted
+    fn measure_invariants(&mut self) {
+        self.now = Invariants::measure(&self.comets, self.softening);
+        self.drift.push([
+            drift(self.now.energy(), self.start.energy()) as f32,
+            drift(self.now.angular_momentum, self.start.angular_momentum) as f32,
+        ]);
+    }
+
+    /// Make the tuned force of every comet `factor` times as strong
+    fn tune(&mut self, factor: f32) {
+        for comet in &mut self.comets {
+            *self.tuned.strength(&mut comet.behaviour) *= factor;
+        }
+        let index = Force::ALL.iter().position(|&force| force == self.tuned);
+        if let Some(index) = index {
+            self.scales[index] *= factor;
+        }
+        self.forget_prediction();
+    }
+
+    /// Throw away the predictions, which no longer match the comets, so that a new one starts
+    fn forget_prediction(&mut self) {
+        self.prediction = None;
+        self.next_prediction = None;
+    }
+
+    /// Work on the next prediction for a frame, starting it again once it has finished
+    fn predict(&mut self) {
+        if !self.show_prediction {
+            return;
+        }
+        let next = self.next_prediction.get_or_insert_with(|| Prediction {
+            forecast: Forecast::new(
+                &self.comets,
+                self.integrator,
+                self.gravity,
+                self.softening,
+                1.0,
+            ),
+            start: self.time,
+        });
+        next.forecast.run(self.prediction_steps, FORECAST_BUDGET);
+        if next.forecast.steps() >= self.prediction_steps || next.forecast.diverged() {
+            self.prediction = self.next_prediction.take();
+        }
+    }
+
+    /// Start launching a comet from the mouse, pausing the simulation until it is launched
+    fn start_launch(&mut self) {
+        let comet = self.new_comet(self.mouse, Vec2::ZERO, launched_behaviour());
+        self.launch = Some(Launch {
+            comet,
+            forecast: Forecast::new(
+                &self.comets,
+                self.integrator,
+                self.gravity,
+                self.softening,
+                1.0,
+            ),
+            path: vec![],
+        });
+    }
+
+    /// The index of the comet nearest `position`, if it is close enough to grab
+    fn comet_at(&self, position: Point2) -> Option<usize> {
+        self.comets
+            .iter()
+            .enumerate()
+            .map(|(i, comet)| (i, comet.position.distance(position)))
+            .filter(|&(_, distance)| distance < GRAB_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
     }
-    // Return the model
-    Model { comets }
 }
 
-fn event(_app: &App, _model: &mut Model, _event: Event) {}
+impl Model {
+    /// Show the rules of each group, and which comets attract the comet nearest the mouse
+    fn draw_rules(&self, scene: &mut Scene, bounds: Rect) {
+        let nearest = self.comets.iter().min_by(|a, b| {
+            a.position
+                .distance(self.mouse)
+                .total_cmp(&b.position.distance(self.mouse))
+        });
+        if let Some(comet) = nearest {
+            for (min, max) in comet.behaviour.rules.filter.distance_bands() {
+                for radius in [min, max] {
+                    scene
+                        .ellipse()
+                        .xy(comet.position)
+                        .radius(radius)
+                        .no_fill()
+                        .stroke(rgba(1.0, 1.0, 1.0, 0.3))
+                        .stroke_weight(1.0);
+                }
+            }
+            // The first comet in the rules' order is the brightest
+            let local = comet.local_comets(&self.comets);
+            for (rank, other) in local.iter().enumerate() {
+                let alpha = 1.0 - 0.8 * rank as f32 / local.len() as f32;
+                scene
+                    .line()
+                    .start(comet.position)
+                    .end(other.position)
+                    .weight(1.0)
+                    .color(rgba(1.0, 1.0, 0.0, alpha));
+            }
+            scene
+                .ellipse()
+                .xy(comet.position)
+                .radius(6.0)
+                .no_fill()
+                .stroke(YELLOW)
+                .stroke_weight(1.0);
+        }
+
+        let groups: BTreeMap<u32, &comet::CometBehaviour> = self
+            .comets
+            .iter()
+            .map(|comet| (comet.behaviour.group, &comet.behaviour))
+            .collect();
+        let text = groups
+            .iter()
+            .map(|(group, behaviour)| {
+                format!(
+                    "group {}: {}, up to {}",
+                    group, behaviour.rules, behaviour.max_local_comets
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let area = Rect::from_w_h(bounds.w() - 20.0, 100.0).bottom_left_of(bounds.pad(10.0));
+        scene
+            .text(&text)
+            .xy(area.xy())
+            .wh(area.wh())
+            .font_size(12)
+            .left_justify()
+            .align_text_bottom()
+            .color(WHITE);
+    }
 
-fn update(_app: &App, model: &mut Model, update: Update) {
-    let delta = update.since_last.secs() / TIME_STEP as f64;
+    /// Draw where each comet is predicted to go as a faint dashed line, and which force the
+    /// arrow keys tune
+    fn draw_prediction(&self, scene: &mut Scene, bounds: Rect) {
+        // Until the first prediction has finished, draw as much of it as there is
+        if let Some(prediction) = self.prediction.as_ref().or(self.next_prediction.as_ref()) {
+            let paths = prediction.forecast.paths();
+            // The simulation has moved on since the prediction started
+            let skip = (self.time - prediction.start).max(0.0).ceil() as usize;
+            if paths.len() == self.comets.len() {
+                for (comet, path) in self.comets.iter().zip(&paths) {
+                    let points: Vec<Point2> = std::iter::once(comet.position)
+                        .chain(path.iter().skip(skip).copied())
+                        .collect();
+                    let color = comet.behaviour.color;
+                    dashed(
+                        scene,
+                        &points,
+                        rgba(color.red, color.green, color.blue, 0.35),
+                    );
+                }
+            }
+        }
 
-    let mut comets: Vec<comet::Comet> = vec![];
-    for comet in &model.comets {
-        let mut new_comet = *comet;
-        new_comet.update(delta, &model.comets);
-        comets.push(new_comet)
+        let text = Force::ALL
+            .iter()
+            .zip(self.scales)
+            .map(|(&force, scale)| {
+                let marker = if force == self.tuned { ">" } else { " " };
+                format!("{} {} x{:.2}", marker, force.name(), scale)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let area = Rect::from_w_h(200.0, 60.0).top_right_of(bounds.pad(10.0));
+        scene
+            .text(&text)
+            .xy(area.xy())
+            .wh(area.wh())
+            .font_size(12)
+            .left_justify()
+            .align_text_top()
+            .color(WHITE);
     }
 
-    model.comets = comets;
+    /// Write the energy and angular momentum in the top left corner, and plot how far they have
+    /// drifted below
+    fn draw_invariants(&self, scene: &mut Scene, bounds: Rect) {
+        let now = self.now;
+        let name = self
+            .integrator
+            .to_possible_value()
+            .map_or(String::new(), |value| value.get_name().to_owned());
+        let text = format!(
+            "{}, {}\nenergy {:.4e} ({:+.3}%)\nangular momentum {:.4e} ({:+.3}%)",
+            self.presets[self.preset].name,
+            name,
+            now.energy(),
+            drift(now.energy(), self.start.energy()),
+            now.angular_momentum,
+            drift(now.angular_momentum, self.start.angular_momentum),
+        );
+        let area = Rect::from_w_h(320.0, 60.0).top_left_of(bounds.pad(10.0));
+        scene
+            .text(&text)
+            .xy(area.xy())
+            .wh(area.wh())
+            .font_size(12)
+            .left_justify()
+            .align_text_top()
+            .color(WHITE);
+        let plot = Rect::from_w_h(320.0, 120.0).below(area).align_left_of(area);
+        self.drift.draw(scene, plot);
+    }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-    draw.rect()
-        .wh(app.window_rect().wh())
-        .xy(app.window_rect().xy())
-        .color(rgba(
-            0.0,
-            0.0,
-            0.0,
-            1.0 - 1.0 / (1.0 + TRAIL_FADE * SPEED as f32),
-        ));
+/// How far `now` has drifted from `start`, in percent
+fn drift(now: f64, start: f64) -> f64 {
+    (now - start) / start.abs() * 100.0
+}
 
-    for comet in model.comets.iter() {
-        comet.draw(&draw);
+/// Draw a line through `points` in dashes `DASH` long
+fn dashed(scene: &mut Scene, points: &[Point2], color: Rgba) {
+    // How far along the whole line each segment starts
+    let mut along = 0.0;
+    for pair in points.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let length = start.distance(end);
+        let mut t = 0.0;
+        while t < length {
+            let phase = (along + t) % (2.0 * DASH);
+            let step = (DASH - phase % DASH).min(length - t);
+            if phase < DASH {
+                scene
+                    .line()
+                    .start(start.lerp(end, t / length))
+                    .end(start.lerp(end, (t + step) / length))
+                    .weight(1.0)
+                    .color(color);
+            }
+            t += step;
+        }
+        along += length;
     }
+}
+
+impl Sketch for Model {
+    fn advance(&mut self, _bounds: Rect, dt: Duration) {
+        let delta = dt.as_secs_f64() / TIME_STEP as f64;
+
+        // The simulation waits while a comet is being launched
+        if self.diverged || self.launch.is_some() {
+            return;
+        }
+        if let Err(e) =
+            self.integrator
+                .step(&mut self.comets, self.gravity, self.softening, delta as f32)
+        {
+            eprintln!("error: {}", e);
+            self.diverged = true;
+            return;
+        }
+        if let Some(collisions) = self.collisions {
+            let merges = collisions.resolve(&mut self.comets);
+            for merge in &merges {
+                println!("{} ({} comets left)", merge, self.comets.len());
+            }
+            if !merges.is_empty() {
+                self.forget_prediction();
+            }
+            // Follow the grabbed comet to its new index, or let go if it was absorbed
+            self.grabbed = self.grabbed.and_then(|grabbed| {
+                if merges.iter().any(|merge| merge.absorbed == grabbed) {
+                    None
+                } else {
+                    Some(
+                        grabbed
+                            - merges
+                                .iter()
+                                .filter(|merge| merge.absorbed < grabbed)
+                                .count(),
+                    )
+                }
+            });
+        }
+        if let Some(grabbed) = self.grabbed {
+            self.comets[grabbed].move_to(self.mouse, Vec2::ZERO);
+        }
+        for comet in &mut self.comets {
+            comet.grow_older(delta as f32);
+        }
+        self.time += delta as f32;
+        if self.show_invariants {
+            self.measure_invariants();
+        }
+        self.predict();
+    }
+
+    fn scene(&self, bounds: Rect) -> Scene {
+        let mut scene = Scene::new();
+        scene.background(BLACK);
+
+        for comet in self.comets.iter() {
+            comet.draw(&mut scene);
+        }
+        if self.show_prediction {
+            self.draw_prediction(&mut scene, bounds);
+        }
+        if let Some(launch) = &self.launch {
+            scene
+                .polyline()
+                .weight(1.0)
+                .points(launch.path.iter().copied())
+                .color(rgba(1.0, 1.0, 0.9, 0.4));
+            scene
+                .arrow()
+                .start(launch.comet.position)
+                .end(self.mouse)
+                .weight(2.0)
+                .color(rgba(1.0, 1.0, 0.9, 0.8));
+        }
+        if self.show_invariants {
+            self.draw_invariants(&mut scene, bounds);
+        }
+        if self.show_rules {
+            self.draw_rules(&mut scene, bounds);
+        }
+        scene
+    }
+}
+
+fn model(_app: &App) -> Model {
+    let args = Args::parse();
+    let mut model = Model::new(&args);
+    model.recorder = Recorder::new(&args.record).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    });
+    model
+}
+
+fn event(_app: &App, model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(event),
+        ..
+    } = event
+    {
+        match event {
+            KeyPressed(Key::S) => model.recorder.request_svg(),
+            KeyPressed(Key::E) => model.toggle_invariants(),
+            KeyPressed(Key::D) => model.show_rules = !model.show_rules,
+            KeyPressed(Key::P) => {
+                model.show_prediction = !model.show_prediction;
+                model.forget_prediction();
+            }
+            KeyPressed(Key::C) => model.tuned = Force::CentralTendency,
+            KeyPressed(Key::R) => model.tuned = Force::Rotation,
+            KeyPressed(Key::G) => model.tuned = Force::Gravity,
+            KeyPressed(Key::Up) => model.tune(1.25),
+            KeyPressed(Key::Down) => model.tune(0.8),
+            KeyPressed(Key::Key1) => model.reset(0),
+            KeyPressed(Key::Key2) => model.reset(1),
+            KeyPressed(Key::Key3) => model.reset(2),
+            KeyPressed(Key::Key4) => model.reset(3),
+            KeyPressed(Key::Key5) => model.reset(4),
+            KeyPressed(Key::Key6) => model.reset(5),
+            KeyPressed(Key::Key7) => model.reset(6),
+            KeyPressed(Key::Key8) => model.reset(7),
+            KeyPressed(Key::Key9) => model.reset(8),
+            MousePressed(MouseButton::Left) => model.start_launch(),
+            MouseReleased(MouseButton::Left) => {
+                if let Some(mut launch) = model.launch.take() {
+                    launch.aim(model.mouse);
+                    model.add(launch.comet);
+                }
+            }
+            MousePressed(MouseButton::Right) => {
+                let comet = model.new_comet(model.mouse, Vec2::ZERO, attractor_behaviour());
+                model.add(comet);
+            }
+            MousePressed(MouseButton::Middle) => model.grabbed = model.comet_at(model.mouse),
+            MouseReleased(MouseButton::Middle) => model.grabbed = None,
+            _ => (),
+        }
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.mouse = app.mouse.position();
+    if let Some(launch) = &mut model.launch {
+        launch.aim(model.mouse);
+    }
+    let dt = model.recorder.frame_time(app, update.since_last);
+    model.advance(app.window_rect(), dt);
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    let scene = model.scene(frame.rect());
+    scene.draw(&draw);
+    if let Err(e) = model.recorder.save_svg(&scene, frame.rect()) {
+        eprintln!("error: {}", e);
+    }
     draw.to_frame(app, &frame).unwrap();
 }
@@ -0,0 +1,156 @@
+use crate::{
+    comet::{attraction, Comet, Gravity},
+    integrator::Integrator,
+};
+use nannou::geom::Point2;
+use std::time::{Duration, Instant};
+
+/// The simulation run ahead on a copy of the comets, a step at a time, to see where they are
+/// going.
+///
+/// Collisions are left out, so the comets are followed as if they passed through each other,
+/// and the forecast stops if a comet diverges.
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    /// The comets at each step so far, starting with where they are now
+    snapshots: Vec<Vec<Comet>>,
+    integrator: Integrator,
+    gravity: Gravity,
+    softening: f32,
+    /// How many time steps each step of the forecast covers
+    delta: f32,
+    diverged: bool,
+}
+
+impl Forecast {
+    pub fn new(
+        comets: &[Comet],
+        integrator: Integrator,
+        gravity: Gravity,
+        softening: f32,
+        delta: f32,
+    ) -> Self {
+        Forecast {
+            snapshots: vec![comets.iter().map(Comet::without_trail).collect()],
+            integrator,
+            gravity,
+            softening,
+            delta,
+            diverged: false,
+        }
+    }
+
+    /// How many steps have been forecast
+    pub fn steps(&self) -> usize {
+        self.snapshots.len() - 1
+    }
+
+    /// Whether a comet diverged, which stops the forecast short
+    pub fn diverged(&self) -> bool {
+        self.diverged
+    }
+
+    /// Forecast more steps, until there are `steps` of them, a comet diverges, or `budget` has
+    /// passed (so that a long forecast can be spread over several frames)
+    pub fn run(&mut self, steps: usize, budget: Duration) {
+        let start = Instant::now();
+        while self.steps() < steps && !self.diverged && start.elapsed() < budget {
+            let mut comets = self.snapshots[self.steps()].clone();
+            match self
+                .integrator
+                .step(&mut comets, self.gravity, self.softening, self.delta)
+            {
+                Ok(()) => self.snapshots.push(comets),
+                Err(_) => self.diverged = true,
+            }
+        }
+    }
+
+    /// The path of each comet so far
+    pub fn paths(&self) -> Vec<Vec<Point2>> {
+        (0..self.snapshots[0].len())
+            .map(|i| {
+                self.snapshots
+                    .iter()
+                    .map(|comets| comets[i].position)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The path that `comet` would follow if it were added to the comets now, for as many steps
+    /// as have been forecast.
+    ///
+    /// The comet is treated as too light to pull the others off their forecast paths, and is
+    /// moved with semi-implicit Euler whatever the integrator.
+    pub fn path_of(&self, comet: &Comet) -> Vec<Point2> {
+        let mut comet = comet.without_trail();
+        let mut path = vec![comet.position];
+        for comets in &self.snapshots[..self.steps()] {
+            let attraction = match self.gravity {
+                Gravity::Local => comet.local_attraction(comets, self.softening),
+                Gravity::BarnesHut { .. } => comets.iter().fold(Point2::ZERO, |acc, other| {
+                    acc + attraction(
+                        comet.position,
+                        other.position,
+                        other.behaviour.mass,
+                        self.softening,
+                    )
+                }),
+            };
+            let velocity = comet.velocity + comet.acceleration(attraction) * self.delta;
+            let position = comet.position + velocity * self.delta;
+            if !position.is_finite() || !velocity.is_finite() {
+                break;
+            }
+            comet.move_to(position, velocity);
+            path.push(position);
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comet::CometBehaviour;
+    use nannou::geom::Vec2;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn a_light_comet_follows_its_own_forecast() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let still = CometBehaviour {
+            central_tendency: 0.0,
+            gravity: 0.0,
+            mass: 1000.0,
+            ..CometBehaviour::default()
+        };
+        let light = CometBehaviour {
+            central_tendency: 0.0,
+            gravity: 0.1,
+            mass: 1.0,
+            ..CometBehaviour::default()
+        };
+        let comets = vec![
+            Comet::new(Vec2::ZERO, Vec2::ZERO, still, &mut rng),
+            Comet::new(Vec2::new(100.0, 0.0), Vec2::new(0.0, 10.0), light, &mut rng),
+        ];
+
+        for gravity in [Gravity::Local, Gravity::BarnesHut { theta: 0.0 }] {
+            let mut forecast =
+                Forecast::new(&comets, Integrator::SemiImplicitEuler, gravity, 10.0, 1.0);
+            forecast.run(50, Duration::MAX);
+            assert_eq!(forecast.steps(), 50);
+
+            let forecast_path = &forecast.paths()[1];
+            let path = forecast.path_of(&comets[1]);
+            assert_eq!(path.len(), forecast_path.len());
+            for (a, b) in path.iter().zip(forecast_path) {
+                assert!(a.distance(*b) < 1e-3, "{} and {} differ", a, b);
+            }
+            // It went around the still comet rather than straight on
+            assert!(path.last().unwrap().length() < 150.0);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Named arrangements of comets to start the simulation from, as data that can be loaded from a
+//! config file.
+//!
+//! The built-in presets are the RON files in `presets/`, which are compiled into the sketch.
+
+use crate::comet::{Comet, CometBehaviour};
+use nannou::prelude::{Point2, Vec2, Vec2Rotate, PI};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The built-in presets, in the order of the number keys that pick them
+const BUILT_IN: [&str; 5] = [
+    include_str!("../presets/swirl.ron"),
+    include_str!("../presets/binary-star.ron"),
+    include_str!("../presets/galaxy-spiral.ron"),
+    include_str!("../presets/ring-collapse.ron"),
+    include_str!("../presets/figure-eight.ron"),
+];
+
+/// A named arrangement of comets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub name: String,
+    pub groups: Vec<Group>,
+}
+
+/// Comets that share a behaviour, and how to place them at the start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Group {
+    #[serde(default)]
+    pub behaviour: CometBehaviour,
+    pub spawn: Spawn,
+}
+
+/// Where a group of comets start, and how fast they are moving
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Spawn {
+    /// Evenly spaced around a ring, moving anticlockwise around its centre at `speed` (clockwise
+    /// if negative)
+    Ring {
+        count: usize,
+        #[serde(default, with = "sketch::serialize::vec2")]
+        centre: Vec2,
+        radius: f32,
+        #[serde(default)]
+        speed: f32,
+        /// How far around the ring the first comet is, as a fraction of the space between two
+        /// comets
+        #[serde(default)]
+        phase: f32,
+        /// How far in or out of the ring each comet may be, at random
+        #[serde(default)]
+        jitter: f32,
+    },
+    /// At random along the arms of a spiral, each moving anticlockwise around its centre at
+    /// `speed`
+    Spiral {
+        count: usize,
+        #[serde(default, with = "sketch::serialize::vec2")]
+        centre: Vec2,
+        arms: usize,
+        min_radius: f32,
+        max_radius: f32,
+        /// How many times each arm winds clockwise around the centre, from its inner end to its
+        /// outer end
+        turns: f32,
+        speed: f32,
+        /// How far from the middle of its arm each comet may be, at random
+        #[serde(default)]
+        spread: f32,
+    },
+    /// Exactly where they are given
+    Bodies { bodies: Vec<Body> },
+}
+
+/// A single comet's starting position and velocity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Body {
+    #[serde(with = "sketch::serialize::vec2")]
+    pub position: Point2,
+    #[serde(default, with = "sketch::serialize::vec2")]
+    pub velocity: Vec2,
+}
+
+impl Preset {
+    /// The presets that come with the sketch
+    pub fn built_in() -> Vec<Preset> {
+        BUILT_IN
+            .iter()
+            .map(|text| ron::from_str(text).expect("the built-in presets are valid"))
+            .collect()
+    }
+
+    /// Create the comets of the preset
+    pub fn spawn(&self, rng: &mut impl Rng) -> Vec<Comet> {
+        let mut comets = vec![];
+        for group in &self.groups {
+            for (position, velocity) in group.spawn.bodies(rng) {
+                comets.push(Comet::new(position, velocity, group.behaviour.clone(), rng));
+            }
+        }
+        comets
+    }
+}
+
+impl Spawn {
+    /// The position and velocity of each comet
+    fn bodies(&self, rng: &mut impl Rng) -> Vec<(Point2, Vec2)> {
+        match *self {
+            Spawn::Ring {
+                count,
+                centre,
+                radius,
+                speed,
+                phase,
+                jitter,
+            } => (0..count)
+                .map(|i| {
+                    let angle = PI * 2.0 * ((i as f32 + phase) / count as f32);
+                    let radius = if jitter > 0.0 {
+                        radius + rng.gen_range(-jitter..=jitter)
+                    } else {
+                        radius
+                    };
+                    (
+                        centre + Vec2::new(1.0, 0.0).rotate(angle) * radius,
+                        Vec2::new(0.0, 1.0).rotate(angle) * speed,
+                    )
+                })
+                .collect(),
+            Spawn::Spiral {
+                count,
+                centre,
+                arms,
+                min_radius,
+                max_radius,
+                turns,
+                speed,
+                spread,
+            } => {
+                let arms = arms.max(1);
+                (0..count)
+                    .map(|i| {
+                        let along: f32 = rng.gen();
+                        let angle = PI * 2.0 * ((i % arms) as f32 / arms as f32 - turns * along);
+                        let offset = Vec2::new(1.0, 0.0).rotate(angle)
+                            * (min_radius + (max_radius - min_radius) * along)
+                            + Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0))
+                                * spread;
+                        (centre + offset, offset.perp().normalize_or_zero() * speed)
+                    })
+                    .collect()
+            }
+            Spawn::Bodies { ref bodies } => bodies
+                .iter()
+                .map(|body| (body.position, body.velocity))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn built_in_presets_spawn_comets() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let presets = Preset::built_in();
+        assert_eq!(presets.len(), BUILT_IN.len());
+        for preset in presets {
+            let comets = preset.spawn(&mut rng);
+            assert!(!comets.is_empty(), "{} has no comets", preset.name);
+            assert!(
+                comets
+                    .iter()
+                    .all(|comet| comet.position.is_finite() && comet.velocity.is_finite()),
+                "{} places comets badly",
+                preset.name
+            );
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::comet::{attraction, Comet};
+use nannou::geom::{Point2, Vec2};
+
+/// How deep the tree goes before comets are left sharing a leaf, so that comets in the same
+/// place don't split cells forever
+const MAX_DEPTH: usize = 32;
+
+/// A Barnes–Hut quadtree over the comets, for approximating the attraction of every comet to
+/// every other in O(n log n).
+///
+/// Each cell knows the total mass and centre of mass of the comets inside it. A cell that looks
+/// small enough from where the attraction is measured (its width over its distance is below the
+/// opening angle θ) attracts as a single body at its centre of mass; otherwise its children are
+/// visited instead. A θ of zero opens every cell, giving the exact (brute force) attraction.
+#[derive(Debug)]
+pub struct QuadTree {
+    /// The position and mass of each comet, in the order they were given
+    bodies: Vec<(Point2, f32)>,
+    /// The cells, the root first
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+struct Node {
+    centre: Point2,
+    /// Half the width of the (square) cell
+    half_width: f32,
+    mass: f32,
+    centre_of_mass: Point2,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    /// The indices of the comets in a cell that isn't split, usually just one
+    Leaf(Vec<usize>),
+    /// The indices of the non-empty quarters of the cell
+    Internal(Vec<usize>),
+}
+
+impl QuadTree {
+    pub fn new(comets: &[Comet]) -> Self {
+        let bodies: Vec<(Point2, f32)> = comets
+            .iter()
+            .map(|comet| (comet.position, comet.behaviour.mass))
+            .collect();
+        let (min, max) = bodies.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), &(position, _)| (min.min(position), max.max(position)),
+        );
+        let (centre, half_width) = if bodies.is_empty() {
+            (Vec2::ZERO, 1.0)
+        } else {
+            (
+                (min + max) / 2.0,
+                ((max - min).max_element() / 2.0).max(1.0),
+            )
+        };
+
+        let mut tree = QuadTree {
+            bodies,
+            nodes: vec![],
+        };
+        let indices = (0..tree.bodies.len()).collect();
+        tree.build(indices, centre, half_width, 0);
+        tree
+    }
+
+    /// Add a cell for the comets at `indices`, and the cells under it, returning its index
+    fn build(
+        &mut self,
+        indices: Vec<usize>,
+        centre: Point2,
+        half_width: f32,
+        depth: usize,
+    ) -> usize {
+        let mass: f32 = indices.iter().map(|&i| self.bodies[i].1).sum();
+        let centre_of_mass = if mass > 0.0 {
+            indices.iter().fold(Vec2::ZERO, |sum, &i| {
+                sum + self.bodies[i].0 * self.bodies[i].1
+            }) / mass
+        } else {
+            centre
+        };
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            centre,
+            half_width,
+            mass,
+            centre_of_mass,
+            kind: Kind::Leaf(vec![]),
+        });
+
+        if indices.len() <= 1 || depth >= MAX_DEPTH {
+            self.nodes[node].kind = Kind::Leaf(indices);
+            return node;
+        }
+
+        let mut quarters: [Vec<usize>; 4] = Default::default();
+        for i in indices {
+            let position = self.bodies[i].0;
+            let quarter = (position.x >= centre.x) as usize + 2 * (position.y >= centre.y) as usize;
+            quarters[quarter].push(i);
+        }
+        let quarter_width = half_width / 2.0;
+        let children = quarters
+            .into_iter()
+            .enumerate()
+            .filter(|(_, indices)| !indices.is_empty())
+            .map(|(quarter, indices)| {
+                let offset = Vec2::new(
+                    if quarter & 1 == 1 { 1.0 } else { -1.0 },
+                    if quarter & 2 == 2 { 1.0 } else { -1.0 },
+                ) * quarter_width;
+                self.build(indices, centre + offset, quarter_width, depth + 1)
+            })
+            .collect();
+        self.nodes[node].kind = Kind::Internal(children);
+        node
+    }
+
+    /// The attraction of the comet at `index` to every other comet (before gravity and its own
+    /// mass are applied), approximated with opening angle `theta`, with Plummer `softening`
+    pub fn attraction(&self, index: usize, theta: f32, softening: f32) -> Vec2 {
+        let Some(&(position, _)) = self.bodies.get(index) else {
+            return Vec2::ZERO;
+        };
+        let mut force = Vec2::ZERO;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            match &node.kind {
+                Kind::Leaf(indices) => {
+                    for &i in indices.iter().filter(|&&i| i != index) {
+                        let (other, mass) = self.bodies[i];
+                        force += attraction(position, other, mass, softening);
+                    }
+                }
+                Kind::Internal(children) => {
+                    // Cells holding the comet itself are always opened, so it never attracts
+                    // itself
+                    let distance = position.distance(node.centre_of_mass);
+                    let offset = (position - node.centre).abs();
+                    let inside = offset.x <= node.half_width && offset.y <= node.half_width;
+                    if !inside && node.half_width * 2.0 < theta * distance {
+                        force += attraction(position, node.centre_of_mass, node.mass, softening);
+                    } else {
+                        stack.extend(children);
+                    }
+                }
+            }
+        }
+        force
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comet::CometBehaviour;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn comets(count: usize, rng: &mut StdRng) -> Vec<Comet> {
+        (0..count)
+            .map(|_| {
+                let position =
+                    Vec2::new(rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0));
+                let behaviour = CometBehaviour {
+                    mass: rng.gen_range(1.0..100.0),
+                    ..CometBehaviour::default()
+                };
+                Comet::new(position, Vec2::ZERO, behaviour, rng)
+            })
+            .collect()
+    }
+
+    fn brute_force(comets: &[Comet], index: usize) -> Vec2 {
+        let position = comets[index].position;
+        comets
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .fold(Vec2::ZERO, |sum, (_, comet)| {
+                sum + attraction(position, comet.position, comet.behaviour.mass, 1.0)
+            })
+    }
+
+    #[test]
+    fn approximates_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let comets = comets(1000, &mut rng);
+        let tree = QuadTree::new(&comets);
+        for theta in [0.0, 0.3, 0.5, 0.8] {
+            let mut error = 0.0;
+            let mut total = 0.0;
+            for i in 0..comets.len() {
+                let exact = brute_force(&comets, i);
+                error += (tree.attraction(i, theta, 1.0) - exact).length();
+                total += exact.length();
+            }
+            let relative_error = error / total;
+            let tolerance = if theta == 0.0 { 1e-4 } else { 0.02 * theta };
+            assert!(
+                relative_error < tolerance,
+                "θ = {}: relative error {} is over {}",
+                theta,
+                relative_error,
+                tolerance
+            );
+        }
+    }
+
+    #[test]
+    fn comets_in_the_same_place() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut comets = comets(3, &mut rng);
+        comets[1].position = comets[0].position;
+        let tree = QuadTree::new(&comets);
+        for i in 0..comets.len() {
+            assert!(tree.attraction(i, 0.5, 0.0).is_finite());
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Rules for which comets attract a comet, as data that can be loaded from a config file and
+//! shown on screen.
+//!
+//! In TOML, a filter that only attracts a comet to heavier comets in a higher group between 100
+//! and 250 away looks like:
+//!
+//! ```toml
+//! filter = { all = [
+//!     { distance = { min = 100.0, max = 250.0 } },
+//!     { group = "higher" },
+//!     { mass = "heavier" },
+//! ] }
+//! sort = { key = "distance" }
+//! ```
+
+use crate::comet::Comet;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt::Display};
+
+/// Which comets attract a comet, and which of them come first when only some can. By default,
+/// the nearest comets, whatever they are.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub sort: Sort,
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}", self.filter, self.sort)
+    }
+}
+
+/// A predicate on another comet, from the point of view of the comet being attracted
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    /// Every comet
+    #[default]
+    Any,
+    /// Comets that pass all of the filters
+    All(Vec<Filter>),
+    /// Comets that pass at least one of the filters
+    Either(Vec<Filter>),
+    /// Comets that don't pass the filter
+    Not(Box<Filter>),
+    /// Comets at least `min` and less than `max` away
+    Distance { min: f32, max: f32 },
+    /// Comets whose group is related to this comet's group
+    Group(GroupRelation),
+    /// Comets whose mass compares to this comet's mass, or to a fixed mass
+    Mass(MassComparison),
+}
+
+/// How another comet's group relates to a comet's group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRelation {
+    Same,
+    Different,
+    /// The other comet's group is numbered higher
+    Higher,
+    /// The other comet's group is numbered lower
+    Lower,
+    /// Both groups are odd or both are even
+    SameParity,
+}
+
+/// How another comet's mass compares
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MassComparison {
+    /// Heavier than this comet
+    Heavier,
+    /// Lighter than this comet
+    Lighter,
+    /// At least this mass
+    AtLeast(f32),
+    /// Less than this mass
+    Below(f32),
+}
+
+impl Filter {
+    /// Whether `that` comet passes the filter for `this` one
+    pub fn allows(&self, this: &Comet, that: &Comet) -> bool {
+        match self {
+            Filter::Any => true,
+            Filter::All(filters) => filters.iter().all(|filter| filter.allows(this, that)),
+            Filter::Either(filters) => filters.iter().any(|filter| filter.allows(this, that)),
+            Filter::Not(filter) => !filter.allows(this, that),
+            Filter::Distance { min, max } => {
+                let distance = this.position.distance(that.position);
+                *min <= distance && distance < *max
+            }
+            Filter::Group(relation) => {
+                let (this, that) = (this.behaviour.group, that.behaviour.group);
+                match relation {
+                    GroupRelation::Same => this == that,
+                    GroupRelation::Different => this != that,
+                    GroupRelation::Higher => this < that,
+                    GroupRelation::Lower => this > that,
+                    GroupRelation::SameParity => this % 2 == that % 2,
+                }
+            }
+            Filter::Mass(comparison) => {
+                let (this, that) = (this.behaviour.mass, that.behaviour.mass);
+                match *comparison {
+                    MassComparison::Heavier => that > this,
+                    MassComparison::Lighter => that < this,
+                    MassComparison::AtLeast(mass) => that >= mass,
+                    MassComparison::Below(mass) => that < mass,
+                }
+            }
+        }
+    }
+
+    /// The distance bands that a comet has to be inside to pass the filter, for drawing
+    pub fn distance_bands(&self) -> Vec<(f32, f32)> {
+        match self {
+            Filter::All(filters) | Filter::Either(filters) => {
+                filters.iter().flat_map(Filter::distance_bands).collect()
+            }
+            Filter::Distance { min, max } => vec![(*min, *max)],
+            _ => vec![],
+        }
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |f: &mut std::fmt::Formatter<'_>, filters: &[Filter], separator: &str| {
+            write!(f, "(")?;
+            for (i, filter) in filters.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", separator)?;
+                }
+                write!(f, "{}", filter)?;
+            }
+            write!(f, ")")
+        };
+        match self {
+            Filter::Any => write!(f, "any comet"),
+            Filter::All(filters) => list(f, filters, "and"),
+            Filter::Either(filters) => list(f, filters, "or"),
+            Filter::Not(filter) => write!(f, "not {}", filter),
+            Filter::Distance { min, max } => write!(f, "{} to {} away", min, max),
+            Filter::Group(relation) => match relation {
+                GroupRelation::Same => write!(f, "same group"),
+                GroupRelation::Different => write!(f, "different group"),
+                GroupRelation::Higher => write!(f, "higher group"),
+                GroupRelation::Lower => write!(f, "lower group"),
+                GroupRelation::SameParity => write!(f, "group of the same parity"),
+            },
+            Filter::Mass(comparison) => match comparison {
+                MassComparison::Heavier => write!(f, "heavier"),
+                MassComparison::Lighter => write!(f, "lighter"),
+                MassComparison::AtLeast(mass) => write!(f, "mass at least {}", mass),
+                MassComparison::Below(mass) => write!(f, "mass below {}", mass),
+            },
+        }
+    }
+}
+
+/// The order in which comets are considered for attraction
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sort {
+    pub key: SortKey,
+    /// Largest first rather than smallest first
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl Sort {
+    /// How `a` compares to `b` as seen from `this` comet
+    pub fn compare(&self, this: &Comet, a: &Comet, b: &Comet) -> Ordering {
+        let key = |comet: &Comet| match self.key {
+            SortKey::Distance => this.position.distance(comet.position),
+            SortKey::Mass => comet.behaviour.mass,
+            SortKey::Age => comet.age(),
+        };
+        let ordering = key(a).total_cmp(&key(b));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (key, order) = match (self.key, self.descending) {
+            (SortKey::Distance, false) => ("distance", "nearest"),
+            (SortKey::Distance, true) => ("distance", "furthest"),
+            (SortKey::Mass, false) => ("mass", "lightest"),
+            (SortKey::Mass, true) => ("mass", "heaviest"),
+            (SortKey::Age, false) => ("age", "youngest"),
+            (SortKey::Age, true) => ("age", "oldest"),
+        };
+        write!(f, "by {}, {} first", key, order)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Distance,
+    Mass,
+    /// How long the comet has existed for, in simulated time
+    Age,
+}
+
+/// Rules for the comets of one group, as loaded from a config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupRules {
+    pub group: u32,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub sort: Sort,
+    /// How many of the comets that pass the filter attract a comet (unchanged if not given)
+    pub max_local_comets: Option<usize>,
+}
+
+/// A config file of rules for each group, in place of the rules the sketch starts with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesConfig {
+    pub groups: Vec<GroupRules>,
+}
+
+impl RulesConfig {
+    /// Give each comet the rules for its group, if there are any
+    pub fn apply(&self, comets: &mut [Comet]) {
+        for comet in comets {
+            if let Some(rules) = self
+                .groups
+                .iter()
+                .find(|rules| rules.group == comet.behaviour.group)
+            {
+                comet.behaviour.rules = Rules {
+                    filter: rules.filter.clone(),
+                    sort: rules.sort,
+                };
+                if let Some(max) = rules.max_local_comets {
+                    comet.behaviour.max_local_comets = max;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn load(name: &str) -> RulesConfig {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("rules")
+            .join(name);
+        sketch::config::load(&path).unwrap()
+    }
+
+    #[test]
+    fn the_example_rules_parse() {
+        assert_eq!(
+            load("original.ron"),
+            RulesConfig {
+                groups: vec![GroupRules {
+                    group: 0,
+                    filter: Filter::All(vec![
+                        Filter::Distance {
+                            min: 100.0,
+                            max: 250.0
+                        },
+                        Filter::Group(GroupRelation::Higher),
+                        Filter::Group(GroupRelation::SameParity),
+                    ]),
+                    sort: Sort {
+                        key: SortKey::Distance,
+                        descending: false,
+                    },
+                    max_local_comets: Some(8),
+                }],
+            }
+        );
+
+        assert_eq!(
+            load("heaviest-first.toml"),
+            RulesConfig {
+                groups: vec![
+                    GroupRules {
+                        group: 0,
+                        filter: Filter::All(vec![
+                            Filter::Distance {
+                                min: 20.0,
+                                max: 400.0
+                            },
+                            Filter::Mass(MassComparison::Heavier),
+                        ]),
+                        sort: Sort {
+                            key: SortKey::Mass,
+                            descending: true,
+                        },
+                        max_local_comets: Some(3),
+                    },
+                    GroupRules {
+                        group: 8,
+                        filter: Filter::Group(GroupRelation::Same),
+                        sort: Sort::default(),
+                        max_local_comets: None,
+                    },
+                ],
+            }
+        );
+    }
+}
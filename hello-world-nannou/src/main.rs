@@ -1,5 +1,12 @@
 extern crate nannou;
+use clap::Parser;
 use nannou::prelude::*;
+use sketch::{
+    record::{self, RecordArgs},
+    scene::Scene,
+    Sketch,
+};
+use std::time::Duration;
 mod model;
 
 macro_rules! BG_COLOR {
@@ -8,7 +15,22 @@ macro_rules! BG_COLOR {
     };
 }
 
+/// Nannou's coordinate system, a clock, and a marker where the mouse was clicked
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
 fn main() {
+    let args = Args::parse();
+    if args.record.headless {
+        if let Err(e) = record::run_headless(&mut model::Model::new(), &args.record) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     nannou::app(model::model)
         .update(update)
         .event(event)
@@ -16,10 +38,9 @@ fn main() {
         .run();
 }
 
-
-
 fn update(_app: &App, _model: &mut model::Model, _update: Update) {
-    _model.update_count += 1;
+    let dt = _model.recorder.frame_time(_app, _update.since_last);
+    _model.advance(_app.window_rect(), dt);
     if let Some((_, time)) = _model.last_click {
         if time.elapsed() > std::time::Duration::from_secs(3) {
             _model.last_click = None;
@@ -30,14 +51,16 @@ fn update(_app: &App, _model: &mut model::Model, _update: Update) {
 }
 
 fn event(_app: &App, _model: &mut model::Model, _event: Event) {
-    match _event {
-        Event::WindowEvent {
-            simple: Some(event),
-            ..
-        } => match event {
+    if let Event::WindowEvent {
+        simple: Some(event),
+        ..
+    } = _event
+    {
+        match event {
             KeyPressed(Key::Escape) => {
                 _app.quit();
             }
+            KeyPressed(Key::S) => _model.recorder.request_svg(),
             MousePressed(MouseButton::Left) => {
                 // println!("Mouse left button pressed at ({},{})", _app.mouse.x, _app.mouse.y);
                 _model.last_click =
@@ -47,100 +70,127 @@ fn event(_app: &App, _model: &mut model::Model, _event: Event) {
                 _model.last_click = None;
             }
             _ => (),
-        },
-        _ => (),
+        }
     }
 }
 
-fn view(_app: &App, _model: &model::Model, frame: Frame) {
-    let draw = _app.draw();
-    let bounds = frame.rect();
-
-    draw.background().color(BG_COLOR!());
-
-    let elapsed_time = _model.update_count as f32 / 60.0;
-
-    draw.rect()
-        .wh(bounds.wh())
-        .stroke_color(rgb(100 as u8, 100 as u8, 200 as u8))
-        .stroke_weight(3.0)
-        .no_fill();
-
-    draw.text(&format!("Elapsed time: {:.2}s", elapsed_time))
-        .xy(bounds.xy())
-        .wh(bounds.pad(10.0).wh())
-        .font_size(16)
-        .color(WHITE)
-        .left_justify()
-        .align_text_top();
-
-    draw.arrow()
-        .start(pt2(0.0, 0.0))
-        .end(pt2(100.0, 0.0))
-        .weight(2.0)
-        .start_cap_round()
-        .color(WHITE);
-
-    draw.text("(100.0, 0.0)")
-        .x_y(100.0, -20.0)
-        .font_size(12)
-        .color(WHITE);
-
-    draw.arrow()
-        .start(pt2(0.0, 0.0))
-        .end(pt2(0.0, 100.0))
-        .weight(2.0)
-        .start_cap_round()
-        .color(WHITE);
-
-    draw.text("(0.0, 100.0)")
-        .roll(PI / 2.0)
-        .x_y(-20.0, 100.0)
-        .font_size(12)
-        .color(WHITE);
-
-    for i in 1..9 {
-        let x = i as f32 * 10.0;
-        let y = i as f32 * 10.0;
-        draw.line()
-            .start(pt2(0.0, y))
-            .end(pt2(90.0, y))
-            .weight(1.0)
-            .caps_round()
-            .color(GRAY);
-        draw.line()
-            .start(pt2(x, 0.0))
-            .end(pt2(x, 90.0))
-            .weight(1.0)
-            .caps_round()
-            .color(GRAY);
+impl Sketch for model::Model {
+    /// The clock counts the time the sketch has been advanced by
+    fn advance(&mut self, _bounds: Rect, dt: Duration) {
+        self.elapsed_time += dt.as_secs_f32();
     }
 
-    if let Some((last_click, _)) = _model.last_click {
-        draw.ellipse()
-            .xy(last_click)
-            .wh(vec2(10.0, 10.0))
-            .color(RED);
-
-        draw.ellipse()
-            .xy(last_click)
-            .wh(vec2(10.0, 10.0) * 2.0)
-            .stroke_color(RED)
-            .stroke_weight(2.0)
+    fn scene(&self, bounds: Rect) -> Scene {
+        let mut scene = Scene::new();
+
+        scene.background(BG_COLOR!());
+
+        let elapsed_time = self.elapsed_time;
+
+        scene
+            .rect()
+            .wh(bounds.wh())
+            .stroke_color(rgb(100u8, 100u8, 200u8))
+            .stroke_weight(3.0)
             .no_fill();
 
-        draw.line()
-            .start(last_click + vec2(10.0, 0.0))
-            .end(last_click + vec2(-10.0, 0.0))
+        scene
+            .text(&format!("Elapsed time: {:.2}s", elapsed_time))
+            .xy(bounds.xy())
+            .wh(bounds.pad(10.0).wh())
+            .font_size(16)
+            .color(WHITE)
+            .left_justify()
+            .align_text_top();
+
+        scene
+            .arrow()
+            .start(pt2(0.0, 0.0))
+            .end(pt2(100.0, 0.0))
             .weight(2.0)
-            .color(RED);
-
-        draw.line()
-            .start(last_click + vec2(0.0, 10.0))
-            .end(last_click + vec2(0.0, -10.0))
+            .start_cap_round()
+            .color(WHITE);
+
+        scene
+            .text("(100.0, 0.0)")
+            .x_y(100.0, -20.0)
+            .font_size(12)
+            .color(WHITE);
+
+        scene
+            .arrow()
+            .start(pt2(0.0, 0.0))
+            .end(pt2(0.0, 100.0))
             .weight(2.0)
-            .color(RED);
+            .start_cap_round()
+            .color(WHITE);
+
+        scene
+            .text("(0.0, 100.0)")
+            .rotate(PI / 2.0)
+            .x_y(-20.0, 100.0)
+            .font_size(12)
+            .color(WHITE);
+
+        for i in 1..9 {
+            let x = i as f32 * 10.0;
+            let y = i as f32 * 10.0;
+            scene
+                .line()
+                .start(pt2(0.0, y))
+                .end(pt2(90.0, y))
+                .weight(1.0)
+                .caps_round()
+                .color(GRAY);
+            scene
+                .line()
+                .start(pt2(x, 0.0))
+                .end(pt2(x, 90.0))
+                .weight(1.0)
+                .caps_round()
+                .color(GRAY);
+        }
+
+        if let Some((last_click, _)) = self.last_click {
+            scene
+                .ellipse()
+                .xy(last_click)
+                .wh(vec2(10.0, 10.0))
+                .color(RED);
+
+            scene
+                .ellipse()
+                .xy(last_click)
+                .wh(vec2(10.0, 10.0) * 2.0)
+                .stroke_color(RED)
+                .stroke_weight(2.0)
+                .no_fill();
+
+            scene
+                .line()
+                .start(last_click + vec2(10.0, 0.0))
+                .end(last_click + vec2(-10.0, 0.0))
+                .weight(2.0)
+                .color(RED);
+
+            scene
+                .line()
+                .start(last_click + vec2(0.0, 10.0))
+                .end(last_click + vec2(0.0, -10.0))
+                .weight(2.0)
+                .color(RED);
+        }
+
+        scene
     }
+}
 
+fn view(_app: &App, _model: &model::Model, frame: Frame) {
+    let draw = _app.draw();
+    let scene = _model.scene(frame.rect());
+    scene.draw(&draw);
+    if let Err(e) = _model.recorder.save_svg(&scene, frame.rect()) {
+        eprintln!("error: {}", e);
+    }
     draw.to_frame(_app, &frame).unwrap()
 }
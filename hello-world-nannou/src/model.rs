@@ -1,14 +1,31 @@
+use clap::Parser;
 use nannou::{geom::Point2, App};
+use sketch::record::Recorder;
 
 pub struct Model {
-    pub update_count: i64,
+    /// The simulated time since the sketch started, in seconds
+    pub elapsed_time: f32,
     pub last_click: Option<(Point2, std::time::Instant)>,
+    pub recorder: Recorder,
+}
+
+impl Model {
+    pub fn new() -> Self {
+        Model {
+            elapsed_time: 0.0,
+            last_click: None,
+            recorder: Recorder::default(),
+        }
+    }
 }
 
 pub fn model(_app: &App) -> Model {
     _app.main_window().set_resizable(false);
-    Model {
-        update_count: 0,
-        last_click: None,
-    }
-}
\ No newline at end of file
+    let args = crate::Args::parse();
+    let mut model = Model::new();
+    model.recorder = Recorder::new(&args.record).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    });
+    model
+}
@@ -0,0 +1,322 @@
+use std::{f32::consts::PI, fmt::Display, str::FromStr};
+
+/// How sharp the peak of the original pulse is
+const PULSE_AGGRESSION: u8 = 7;
+/// The most aggression a pulse can have before its curve no longer fits in an `f32`
+const MAX_AGGRESSION: u8 = 30;
+
+/// The shape of a jelly's swimming stroke: how hard it is pushing at each point of a period,
+/// from 0 (relaxed) to 1 (fully contracted).
+///
+/// A gait can be written as its name followed by its parameters, e.g. `pulse:9`, `sine:2`,
+/// `glide:0.15,0.35` or `keyframes:0,0/0.2,1/0.5,0`; a name on its own is the built-in gait of
+/// that kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gait {
+    /// A slow build up to a sharp peak late in the period, then a quick release. A higher
+    /// `aggression` makes the peak later and sharper.
+    Pulse { aggression: u8 },
+    /// A smooth rise and fall, raised to the power of `sharpness`, so that a sharpness above 1
+    /// gives a shorter, sharper push and one below 1 a longer, flatter one
+    Sine { sharpness: f32 },
+    /// Contract for the first `contract` of the period, relax for the next `relax`, and glide
+    /// without pushing for the rest of it
+    Glide { contract: f32, relax: f32 },
+    /// Straight lines between `(phase, value)` keyframes, in order of phase, wrapping around
+    /// from the last to the first
+    Keyframes(Vec<(f32, f32)>),
+}
+
+impl Default for Gait {
+    fn default() -> Self {
+        Gait::Pulse {
+            aggression: PULSE_AGGRESSION,
+        }
+    }
+}
+
+impl Gait {
+    /// One of each kind of gait, with their usual parameters
+    pub fn built_in() -> Vec<Gait> {
+        vec![
+            Gait::default(),
+            Gait::Sine { sharpness: 1.0 },
+            Gait::Glide {
+                contract: 0.15,
+                relax: 0.35,
+            },
+            // A double kick: a strong contraction, a partial release and a second push
+            Gait::Keyframes(vec![
+                (0.0, 0.0),
+                (0.12, 1.0),
+                (0.25, 0.45),
+                (0.35, 0.8),
+                (0.6, 0.0),
+            ]),
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Gait::Pulse { .. } => "pulse",
+            Gait::Sine { .. } => "sine",
+            Gait::Glide { .. } => "glide",
+            Gait::Keyframes(_) => "keyframes",
+        }
+    }
+
+    /// How hard the jelly is pushing at `phase` through the period (wrapped into 0 to 1)
+    pub fn at(&self, phase: f32) -> f32 {
+        let t = phase.rem_euclid(1.0);
+        match self {
+            Gait::Pulse { aggression } => {
+                let raw = |t: f32| -t * (t - 1.0) * (t + 1.0).powi(*aggression as i32);
+                raw(t) / raw(pulse_peak(*aggression))
+            }
+            Gait::Sine { sharpness } => ((1.0 - (2.0 * PI * t).cos()) / 2.0).powf(*sharpness),
+            Gait::Glide { contract, relax } => {
+                let ease = |x: f32| x * x * (3.0 - 2.0 * x);
+                if t < *contract {
+                    ease(t / contract)
+                } else if t < contract + relax {
+                    1.0 - ease((t - contract) / relax)
+                } else {
+                    0.0
+                }
+            }
+            Gait::Keyframes(keyframes) => keyframe_value(keyframes, t),
+        }
+    }
+}
+
+/// Where the pulse with `aggression` peaks, to scale the peak to 1
+fn pulse_peak(aggression: u8) -> f32 {
+    let a = aggression as f32;
+    0.5 * (a.powi(2) + 2.0 * a + 9.0).sqrt() / (a + 2.0) + 0.5 * (a - 1.0) / (a + 2.0)
+}
+
+impl Display for Gait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.name())?;
+        match self {
+            Gait::Pulse { aggression } => write!(f, "{}", aggression),
+            Gait::Sine { sharpness } => write!(f, "{}", sharpness),
+            Gait::Glide { contract, relax } => write!(f, "{},{}", contract, relax),
+            Gait::Keyframes(keyframes) => {
+                for (i, (phase, value)) in keyframes.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "/" };
+                    write!(f, "{}{},{}", separator, phase, value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Gait {
+    type Err = String;
+
+    /// Parse a gait written as `NAME[:PARAMETERS]`, as `Display` writes it
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, parameters) = match text.split_once(':') {
+            Some((name, parameters)) => (name.trim(), Some(parameters)),
+            None => (text.trim(), None),
+        };
+        let Some(parameters) = parameters else {
+            return Gait::built_in()
+                .into_iter()
+                .find(|gait| gait.name() == name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown gait `{}`, expected pulse, sine, glide or keyframes",
+                        name
+                    )
+                });
+        };
+        let number = |text: &str| {
+            text.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| format!("`{}` is not a number", text))
+        };
+        let pair = |text: &str| {
+            let (a, b) = text.split_once(',').ok_or_else(|| {
+                format!("expected two numbers separated by a comma, not `{}`", text)
+            })?;
+            Ok::<_, String>((number(a)?, number(b)?))
+        };
+
+        let gait = match name {
+            "pulse" => Gait::Pulse {
+                aggression: parameters
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&a| a <= MAX_AGGRESSION)
+                    .ok_or_else(|| {
+                        format!(
+                            "a pulse's aggression is a whole number from 0 to {}",
+                            MAX_AGGRESSION
+                        )
+                    })?,
+            },
+            "sine" => Gait::Sine {
+                sharpness: number(parameters)?,
+            },
+            "glide" => {
+                let (contract, relax) = pair(parameters)?;
+                Gait::Glide { contract, relax }
+            }
+            "keyframes" => {
+                Gait::Keyframes(parameters.split('/').map(pair).collect::<Result<_, _>>()?)
+            }
+            _ => return Err(format!("unknown gait `{}`", name)),
+        };
+        gait.validate().map(|()| gait)
+    }
+}
+
+impl Gait {
+    /// Check the parameters keep the gait between 0 and 1 all the way through the period
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Gait::Pulse { .. } => Ok(()),
+            Gait::Sine { sharpness } => {
+                if *sharpness > 0.0 {
+                    Ok(())
+                } else {
+                    Err("a sine's sharpness must be more than zero".to_owned())
+                }
+            }
+            Gait::Glide { contract, relax } => {
+                if *contract > 0.0 && *relax > 0.0 && contract + relax <= 1.0 {
+                    Ok(())
+                } else {
+                    Err(
+                        "a glide contracts and relaxes for more than zero and at most 1 \
+                         period between them"
+                            .to_owned(),
+                    )
+                }
+            }
+            Gait::Keyframes(keyframes) => {
+                let in_range = |n: f32| (0.0..=1.0).contains(&n);
+                if keyframes.is_empty() {
+                    Err("keyframes need at least one `phase,value`".to_owned())
+                } else if !keyframes
+                    .iter()
+                    .all(|&(phase, value)| (0.0..1.0).contains(&phase) && in_range(value))
+                {
+                    Err(
+                        "each keyframe's phase must be from 0 to below 1, and its value \
+                         from 0 to 1"
+                            .to_owned(),
+                    )
+                } else if !keyframes.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+                    Err("keyframes must be in order of phase".to_owned())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// The value of `keyframes` at `t`, between the keyframes either side of it
+fn keyframe_value(keyframes: &[(f32, f32)], t: f32) -> f32 {
+    let (Some(&first), Some(&last)) = (keyframes.first(), keyframes.last()) else {
+        return 0.0;
+    };
+    let after = keyframes.iter().position(|&(phase, _)| phase > t);
+    // Before the first keyframe or after the last, the curve wraps around between them
+    let ((start, from), (end, to)) = match after {
+        Some(0) | None => ((last.0 - 1.0, last.1), (first.0, first.1)),
+        Some(i) => (keyframes[i - 1], keyframes[i]),
+    };
+    let (start, end) = if after.is_none() {
+        (start + 1.0, end + 1.0)
+    } else {
+        (start, end)
+    };
+    if end - start <= 0.0 {
+        return to;
+    }
+    from + (to - from) * (t - start) / (end - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Phases from just before 0 to just after 1, finely enough to find each gait's peak
+    fn phases() -> impl Iterator<Item = f32> {
+        (-10..=1010).map(|i| i as f32 / 1000.0)
+    }
+
+    #[test]
+    fn the_built_in_gaits_stay_between_0_and_1() {
+        let mut gaits = Gait::built_in();
+        gaits.extend(
+            ["pulse:0", "pulse:30", "sine:0.3", "sine:4", "glide:0.5,0.5"]
+                .map(|text| text.parse::<Gait>().unwrap()),
+        );
+        for gait in gaits {
+            for phase in phases() {
+                let value = gait.at(phase);
+                assert!(
+                    (0.0..=1.0 + 1e-6).contains(&value),
+                    "{} is {} at {}",
+                    gait,
+                    value,
+                    phase
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_pulse_peaks_at_exactly_1() {
+        for aggression in 0..=MAX_AGGRESSION {
+            let gait = Gait::Pulse { aggression };
+            assert_eq!(gait.at(pulse_peak(aggression)), 1.0, "{}", gait);
+            let highest = phases().map(|phase| gait.at(phase)).fold(0.0, f32::max);
+            assert!(highest <= 1.0 + 1e-6, "{} reaches {}", gait, highest);
+        }
+    }
+
+    #[test]
+    fn keyframes_wrap_around_between_the_last_and_the_first() {
+        let keyframes = [(0.2, 0.0), (0.6, 1.0)];
+        // From the last keyframe at 0.6 - 1 = -0.4 to the first at 0.2, 0.6 of a period apart
+        assert!((keyframe_value(&keyframes, 0.1) - (1.0 - 0.5 / 0.6)).abs() < 1e-6);
+        assert!((keyframe_value(&keyframes, 0.8) - (1.0 - 0.2 / 0.6)).abs() < 1e-6);
+        assert!(
+            (keyframe_value(&keyframes, 0.0) - keyframe_value(&keyframes, 0.9999)).abs() < 1e-3
+        );
+        // On and between the keyframes themselves
+        assert_eq!(keyframe_value(&keyframes, 0.2), 0.0);
+        assert_eq!(keyframe_value(&keyframes, 0.6), 1.0);
+        assert!((keyframe_value(&keyframes, 0.4) - 0.5).abs() < 1e-6);
+        // A single keyframe holds its value all the way round
+        assert_eq!(keyframe_value(&[(0.3, 0.7)], 0.9), 0.7);
+    }
+
+    #[test]
+    fn gaits_are_read_as_they_are_written() {
+        for gait in Gait::built_in() {
+            assert_eq!(gait.to_string().parse::<Gait>(), Ok(gait.clone()));
+            assert_eq!(gait.name().parse::<Gait>(), Ok(gait));
+        }
+        assert_eq!("sine:2".parse(), Ok(Gait::Sine { sharpness: 2.0 }));
+        for bad in [
+            "walk",
+            "pulse:31",
+            "sine:0",
+            "glide:0.8,0.4",
+            "keyframes:0.5,0/0.2,1",
+        ] {
+            assert!(bad.parse::<Gait>().is_err(), "{} was accepted", bad);
+        }
+    }
+}
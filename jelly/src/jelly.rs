@@ -1,48 +1,64 @@
+use crate::{Boundary, Gait, Steering, Tentacle, World};
 use nannou::prelude::*;
+use rand::Rng;
+use sketch::scene::Scene;
 
-const PULSE_AGGRESSION: u8 = 7;
-fn raw_pulse(t: f32) -> f32 {
-    -(t) * (t - 1.0) * (t + 1.0).powi(PULSE_AGGRESSION as i32)
-}
-
-fn pulse(t: f32) -> f32 {
-    let t = t % 1.0;
-    let a = PULSE_AGGRESSION as f32;
-    let ajust = 0.5 * (a.powi(2) + 2.0 * a + 9.0).sqrt() / (a + 2.0) + 0.5 * (a - 1.0) / (a + 2.0);
-    raw_pulse(t) / raw_pulse(ajust)
-}
+/// How many points make up the dome of the bell
+const DOME_POINTS: usize = 24;
+/// How many points make up the opening at the back of the bell
+const OPENING_POINTS: usize = 10;
 
 pub struct DrawSettings {
-    pub color: Rgba,
+    pub color: Rgb,
+    /// How opaque the jelly is, from 0 (invisible) to 1
+    pub alpha: f32,
+    /// The radius of the bell when it is relaxed
     pub size: f32,
-    pub rotation: f32,
+    /// How much bigger the whole jelly is drawn, tentacles included
     pub scale: f32,
-    pub alpha: f32,
+    /// How far the drawing is turned from the way the jelly faces, in radians
+    pub rotation: f32,
+    /// How much narrower the bell gets when it is fully contracted, as a fraction of its width
+    pub squeeze: f32,
+    pub tentacles: usize,
+    /// How long each tentacle is, before scaling
+    pub tentacle_length: f32,
 }
 
 impl Default for DrawSettings {
     fn default() -> Self {
         DrawSettings {
-            color: rgba(1.0, 1.0, 1.0, 1.0),
-            size: 10.0,
-            rotation: 0.0,
+            color: rgb(0.75, 0.6, 1.0),
+            alpha: 0.5,
+            size: 20.0,
             scale: 1.0,
-            alpha: 1.0,
+            rotation: 0.0,
+            squeeze: 0.35,
+            tentacles: 6,
+            tentacle_length: 70.0,
         }
     }
 }
 pub struct JellyMovement {
+    /// How far through the period the jelly is, from 0 to 1
     pub phase: f32,
+    /// How long a period lasts, in seconds
     pub period: f32,
-    pub phase_fn: fn(f32) -> f32,
+    pub gait: Gait,
+    /// How fast the bell contracted over the last update, in contractions per second (negative
+    /// while it relaxes)
+    pub contraction: f32,
 }
 
 impl JellyMovement {
-    pub fn update(&mut self) {
-        self.phase = ((self.phase + 0.01) % self.period)/self.period;
+    /// Move `dt` seconds through the period
+    pub fn update(&mut self, dt: f32) {
+        let before = self.impetus();
+        self.phase = (self.phase + dt / self.period) % 1.0;
+        self.contraction = (self.impetus() - before) / dt;
     }
-    pub fn impetus(&mut self) -> f32 {
-        (self.phase_fn)(self.phase)
+    pub fn impetus(&self) -> f32 {
+        self.gait.at(self.phase)
     }
 }
 
@@ -50,8 +66,9 @@ impl Default for JellyMovement {
     fn default() -> Self {
         JellyMovement {
             phase: 0.0,
-            period: 1.0,
-            phase_fn: pulse,
+            period: 1.5,
+            gait: Gait::default(),
+            contraction: 0.0,
         }
     }
 }
@@ -59,36 +76,288 @@ pub struct Jelly {
     pub position: Point2,
     pub velocity: Vec2,
     pub mass: f32,
+    /// The way the bell faces, as an angle anticlockwise from the x axis
     pub direction: f32,
+    /// How hard the jelly pushes for each whole contraction of the bell per second
+    pub thrust: f32,
+    /// How much the water slows the jelly, with the square of its speed
+    pub drag: f32,
     pub movement: JellyMovement,
+    pub steering: Steering,
     pub draw_settings: DrawSettings,
+    tentacles: Vec<Tentacle>,
 }
 
 impl Jelly {
-    pub fn update(&mut self) {
-        self.movement.update();
-        self.position += self.velocity * self.movement.impetus();
-    }
-    pub fn draw(&self, draw: &nannou::draw::Draw) {
-        draw.ellipse()
-            .x_y(self.position.x, self.position.y)
-            .w_h(3.0, 2.0)
-            .radius(self.draw_settings.size)
-            .color(self.draw_settings.color)
-            .rotate(self.draw_settings.rotation);
-    }
-}
-    
-
-impl Default for Jelly {
-    fn default() -> Self {
+    /// A still jelly at the origin, facing in a random direction
+    pub fn new(rng: &mut impl Rng) -> Self {
         Jelly {
             position: vec2(0.0, 0.0),
-            velocity: vec2(1.0, 0.0).rotate(random_range(0.0, PI * 2.0)),
+            velocity: vec2(0.0, 0.0),
             mass: 1.0,
-            direction: 0.0,
+            direction: rng.gen_range(0.0..PI * 2.0),
+            thrust: 100.0,
+            drag: 0.02,
             movement: JellyMovement::default(),
+            steering: Steering::new(rng),
             draw_settings: DrawSettings::default(),
+            tentacles: vec![],
+        }
+    }
+
+    /// The way the bell faces, as a unit vector
+    pub fn heading(&self) -> Vec2 {
+        vec2(1.0, 0.0).rotate(self.direction)
+    }
+
+    /// How hard the bell is pushing the jelly forwards
+    pub fn thrust_force(&self) -> f32 {
+        self.thrust * self.movement.contraction.max(0.0)
+    }
+
+    /// How hard the water is holding the jelly back
+    pub fn drag_force(&self) -> f32 {
+        self.drag * self.velocity.length_squared()
+    }
+
+    /// Whether `point` is on the jelly's bell, or near enough to count
+    pub fn contains(&self, point: Point2) -> bool {
+        self.position.distance(point) < self.draw_settings.size * self.draw_settings.scale * 1.5
+    }
+
+    /// Move the jelly on by `dt` seconds. Contracting the bell pushes it forwards, harder the
+    /// faster it contracts, and drag slows it down in between, so it surges and coasts. It only
+    /// turns while the bell is not contracting.
+    pub fn update(&mut self, world: &World, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.movement.update(dt);
+        let turn = self.steering.turn(world, self.position, self.heading(), dt);
+        if self.movement.contraction <= 0.0 {
+            self.direction = (self.direction + turn * dt).rem_euclid(PI * 2.0);
+        }
+
+        let thrust = self.heading() * self.thrust_force();
+        let drag = -self.velocity.normalize_or_zero() * self.drag_force();
+        let mut acceleration = (thrust + drag) / self.mass;
+        if world.boundary == Boundary::Soft {
+            acceleration += self.steering.soft_wall(world.bounds, self.position);
+        }
+        self.velocity += acceleration * dt;
+        self.position += self.velocity * dt;
+
+        let before = self.position;
+        let margin = self.draw_settings.size * self.draw_settings.scale;
+        world
+            .boundary
+            .contain(world.bounds, margin, &mut self.position, &mut self.velocity);
+        let jump = self.position - before;
+        if jump != Vec2::ZERO {
+            for tentacle in &mut self.tentacles {
+                tentacle.shift(jump);
+            }
+        }
+
+        let anchors = self.anchors();
+        let heading = self.heading();
+        if self.tentacles.len() != anchors.len() {
+            self.grow_tentacles(&anchors);
+        }
+        for (tentacle, anchor) in self.tentacles.iter_mut().zip(anchors) {
+            tentacle.update(anchor, heading, dt);
+        }
+    }
+
+    /// Replace the tentacles with new ones hanging straight back from `anchors`
+    fn grow_tentacles(&mut self, anchors: &[Point2]) {
+        let settings = &self.draw_settings;
+        self.tentacles = anchors
+            .iter()
+            .enumerate()
+            .map(|(i, &anchor)| {
+                Tentacle::new(
+                    anchor,
+                    -self.heading(),
+                    settings.tentacle_length * settings.scale,
+                    i as f32 * 0.4,
+                )
+            })
+            .collect();
+    }
+
+    /// The angle the jelly is drawn at
+    fn angle(&self) -> f32 {
+        self.direction + self.draw_settings.rotation
+    }
+
+    /// The outline of the bell around the jelly's position, facing along the x axis: a dome that
+    /// narrows and lengthens as the bell contracts, and a curved opening at the back
+    fn bell(&self) -> Vec<Point2> {
+        let (length, width, rim, depth) = self.bell_shape();
+        let dome = (0..=DOME_POINTS).map(|i| {
+            let angle = map_range(i, 0, DOME_POINTS, -PI / 2.0, PI / 2.0);
+            vec2(length * angle.cos(), width * angle.sin())
+        });
+        let opening = (0..=OPENING_POINTS).map(|i| {
+            let y = map_range(i, 0, OPENING_POINTS, rim.y, -rim.y);
+            opening_point(rim, depth, y)
+        });
+        dome.chain(opening).collect()
+    }
+
+    /// The size of the bell at the current phase: how far the dome reaches forward, how wide it
+    /// is, where the top of the rim is, and how deep the opening curves into the bell
+    fn bell_shape(&self) -> (f32, f32, Vec2, f32) {
+        let settings = &self.draw_settings;
+        let contraction = self.movement.impetus();
+        let size = settings.size * settings.scale;
+        let length = size * (1.0 + settings.squeeze * 0.4 * contraction);
+        let width = size * (1.0 - settings.squeeze * contraction);
+        let rim = vec2(
+            -size * 0.35,
+            width * (1.0 - settings.squeeze * 0.6 * contraction),
+        );
+        (length, width, rim, size * 0.2)
+    }
+
+    /// Where the tentacles hang from, spread along the opening of the bell
+    fn anchors(&self) -> Vec<Point2> {
+        let count = self.draw_settings.tentacles;
+        let (_, _, rim, depth) = self.bell_shape();
+        (0..count)
+            .map(|i| {
+                let y = rim.y * 0.8 * map_range(i as f32 + 0.5, 0.0, count as f32, -1.0, 1.0);
+                self.position + opening_point(rim, depth, y).rotate(self.angle())
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, scene: &mut Scene) {
+        let settings = &self.draw_settings;
+        let color = |alpha: f32| {
+            let Rgb {
+                red, green, blue, ..
+            } = settings.color;
+            rgba(red, green, blue, alpha.min(1.0))
+        };
+        for tentacle in &self.tentacles {
+            scene
+                .polyline()
+                .weight(1.5 * settings.scale)
+                .points(tentacle.points().iter().copied())
+                .color(color(settings.alpha));
+        }
+        scene
+            .polygon()
+            .points(self.bell())
+            .xy(self.position)
+            .rotate(self.angle())
+            .color(color(settings.alpha))
+            .stroke(color(settings.alpha * 2.0))
+            .stroke_weight(1.5 * settings.scale);
+    }
+}
+
+/// The point at height `y` on the opening at the back of a bell, which curves `depth` into the
+/// bell between the top and bottom of its `rim`
+fn opening_point(rim: Vec2, depth: f32, y: f32) -> Point2 {
+    vec2(rim.x + depth * (1.0 - (y / rim.y).powi(2)), y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const DT: f32 = 1.0 / 120.0;
+
+    /// Open water, so that nothing but the jelly's own stroke moves it
+    fn open_water() -> World {
+        World {
+            bounds: Rect::from_w_h(1e6, 1e6),
+            boundary: Boundary::Wrap,
+            target: None,
+        }
+    }
+
+    /// A jelly that swims with a glide: a push, then a long coast
+    fn gliding_jelly() -> Jelly {
+        let mut jelly = Jelly::new(&mut StdRng::seed_from_u64(0));
+        jelly.movement.gait = Gait::Glide {
+            contract: 0.2,
+            relax: 0.3,
+        };
+        jelly
+    }
+
+    #[test]
+    fn a_jelly_speeds_up_as_it_contracts_and_slows_as_it_coasts() {
+        let world = open_water();
+        let mut jelly = gliding_jelly();
+        // Swim a stroke to get going, then watch the next one
+        let steps = (jelly.movement.period / DT) as usize;
+        for _ in 0..steps {
+            jelly.update(&world, DT);
+        }
+
+        let (mut pushed, mut coasted) = (0.0, 0);
+        for _ in 0..steps {
+            let speed = jelly.velocity.length();
+            jelly.update(&world, DT);
+            let change = jelly.velocity.length() - speed;
+            if jelly.movement.contraction > 0.0 {
+                pushed += change;
+            } else {
+                assert!(change <= 0.0, "sped up by {} while coasting", change);
+                coasted += 1;
+            }
+        }
+        assert!(pushed > 0.0, "the stroke didn't speed the jelly up");
+        assert!(coasted > 0, "the jelly never coasted");
+    }
+
+    #[test]
+    fn doubling_the_mass_halves_the_acceleration() {
+        let world = open_water();
+        let accelerate = |mass: f32| {
+            let mut jelly = gliding_jelly();
+            jelly.mass = mass;
+            jelly.velocity = jelly.heading() * 30.0;
+            jelly.movement.phase = 0.05;
+            let before = jelly.velocity;
+            jelly.update(&world, DT);
+            assert!(jelly.movement.contraction > 0.0);
+            (jelly.velocity - before) / DT
+        };
+        let (light, heavy) = (accelerate(1.0), accelerate(2.0));
+        assert!(light.length() > 0.0);
+        assert!(
+            (light - heavy * 2.0).length() < light.length() * 1e-3,
+            "{} and {}",
+            light,
+            heavy
+        );
+    }
+
+    #[test]
+    fn a_jelly_only_turns_while_its_bell_relaxes() {
+        let mut world = open_water();
+        let mut jelly = gliding_jelly();
+        jelly.steering.seek = 5.0;
+        // Behind the jelly, so it always wants to turn
+        world.target = Some(jelly.position - jelly.heading().rotate(0.3) * 500.0);
+
+        let mut turned = false;
+        for _ in 0..(3.0 * jelly.movement.period / DT) as usize {
+            let direction = jelly.direction;
+            jelly.update(&world, DT);
+            if jelly.movement.contraction > 0.0 {
+                assert_eq!(jelly.direction, direction, "turned while contracting");
+            } else {
+                turned |= jelly.direction != direction;
+            }
         }
+        assert!(turned, "the jelly never turned");
     }
 }
@@ -0,0 +1,9 @@
+mod gait;
+mod jelly;
+mod steering;
+mod tentacle;
+
+pub use gait::*;
+pub use jelly::*;
+pub use steering::*;
+pub use tentacle::*;
@@ -1,9 +1,48 @@
-mod jelly;
-
-use jelly::Jelly;
+use clap::Parser;
+use jelly::{Boundary, Gait, Jelly, World};
 use nannou::prelude::*;
+use rand::rngs::StdRng;
+use sketch::{
+    plot::{Plot, YRange},
+    record::{self, RecordArgs, Recorder},
+    scene::Scene,
+    seed::SeedArgs,
+    Sketch,
+};
+use std::time::Duration;
+
+/// How many frames of the selected jelly's speed and forces are plotted
+const TELEMETRY_SAMPLES: usize = 200;
+
+/// Jellyfish pulsing through the water
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+    /// How new jellies swim: pulse, sine, glide or keyframes, optionally with parameters, e.g.
+    /// pulse:9, sine:2, glide:0.15,0.35 or keyframes:0,0/0.2,1/0.5,0 (G changes the gait of the
+    /// selected jelly)
+    #[arg(long, default_value = "pulse")]
+    gait: Gait,
+    /// How long a stroke lasts, in seconds
+    #[arg(long, default_value_t = 1.5, value_parser = sketch::parse_positive::<f32>)]
+    period: f32,
+    /// What happens to jellies at the edge of the window (B cycles through them)
+    #[arg(long, value_enum, default_value_t = Boundary::Soft)]
+    boundary: Boundary,
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
 fn main() {
+    let args = Args::parse();
+    if args.record.headless {
+        if let Err(e) = record::run_headless(&mut Model::new(&args), &args.record) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     nannou::app(model)
         .update(update)
         .event(event)
@@ -12,104 +51,238 @@ fn main() {
 }
 
 struct Model {
+    rng: StdRng,
     jellies: Vec<Jelly>,
-    jelly_positions: Vec<f32>,
+    /// The gait and period of new jellies
+    gait: Gait,
+    period: f32,
+    /// The index of the jelly shown in the graphs
+    selected: usize,
+    boundary: Boundary,
+    /// Where the mouse is, if it is in the window
+    mouse: Option<Point2>,
+    /// Whether the jellies swim towards the mouse
+    seek: bool,
+    /// The speed of the selected jelly and the forces on it
+    telemetry: Plot,
+    recorder: Recorder,
 }
 
-fn model(_app: &App) -> Model {
-    Model {
-        jellies: vec![Jelly::default()],
-        jelly_positions: Vec::new(),
+impl Model {
+    fn new(args: &Args) -> Self {
+        let rng = args.seed.rng();
+        let mut model = Model {
+            jellies: vec![],
+            gait: args.gait.clone(),
+            period: args.period,
+            selected: 0,
+            boundary: args.boundary,
+            mouse: None,
+            seek: false,
+            rng,
+            telemetry: Plot::new(TELEMETRY_SAMPLES)
+                .series("speed", RED)
+                .series("thrust", ORANGE)
+                .series("drag", CORNFLOWERBLUE)
+                .y_range(YRange::AutoFromZero),
+            recorder: Recorder::default(),
+        };
+        model.add_jelly();
+        model
     }
-}
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    for jelly in model.jellies.iter_mut() {
-        jelly.update();
+    /// Add a jelly with the gait and period for new jellies
+    fn add_jelly(&mut self) {
+        let mut jelly = Jelly::new(&mut self.rng);
+        jelly.movement.gait = self.gait.clone();
+        jelly.movement.period = self.period;
+        self.jellies.push(jelly);
     }
-    if model.jelly_positions.len() < 100 {
-        model
-            .jelly_positions
-            .push(model.jellies[0].velocity.length());
-    } else {
-        model.jelly_positions.remove(0);
-        model
-            .jelly_positions
-            .push(model.jellies[0].velocity.length());
+
+    /// Show `index` in the graphs
+    fn select(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.telemetry.clear();
+        }
+    }
+
+    /// Give the selected jelly the next of the built-in gaits
+    fn next_gait(&mut self) {
+        let gaits = Gait::built_in();
+        let movement = &mut self.jellies[self.selected].movement;
+        let index = gaits
+            .iter()
+            .position(|gait| gait.name() == movement.gait.name())
+            .map_or(0, |index| (index + 1) % gaits.len());
+        movement.gait = gaits[index].clone();
+    }
+
+    /// Plot the selected jelly's gait over one period, marking where it is in the period
+    fn draw_gait(&self, scene: &mut Scene, rect: Rect) {
+        let movement = &self.jellies[self.selected].movement;
+        let point = |phase: f32| {
+            pt2(
+                map_range(phase, 0.0, 1.0, rect.left(), rect.right()),
+                map_range(movement.gait.at(phase), 0.0, 1.0, rect.bottom(), rect.top()),
+            )
+        };
+        scene
+            .polyline()
+            .weight(2.0)
+            .points((0..=100).map(|i| point(i as f32 / 100.0)))
+            .color(ORANGE);
+        let x = map_range(movement.phase, 0.0, 1.0, rect.left(), rect.right());
+        scene
+            .line()
+            .start(pt2(x, rect.bottom()))
+            .end(pt2(x, rect.top()))
+            .weight(1.0)
+            .color(GRAY);
+        scene
+            .ellipse()
+            .xy(point(movement.phase))
+            .radius(3.0)
+            .color(WHITE);
+        scene
+            .rect()
+            .no_fill()
+            .xy(rect.xy())
+            .wh(rect.wh())
+            .stroke(WHITE)
+            .stroke_weight(1.0);
+        let label = Rect::from_w_h(rect.w(), 20.0).top_left_of(rect.pad(4.0));
+        scene
+            .text(&format!(
+                "jelly {}: {}",
+                self.selected + 1,
+                movement.gait.name()
+            ))
+            .xy(label.xy())
+            .wh(label.wh())
+            .font_size(12)
+            .left_justify()
+            .color(WHITE);
     }
 }
 
+fn model(_app: &App) -> Model {
+    let args = Args::parse();
+    let mut model = Model::new(&args);
+    model.recorder = Recorder::new(&args.record).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    });
+    model
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    let dt = model.recorder.frame_time(app, update.since_last);
+    model.advance(app.window_rect(), dt);
+}
+
 fn event(_app: &App, model: &mut Model, event: Event) {
-    match event {
-        Event::WindowEvent {
-            simple: Some(event),
-            ..
-        } => match event {
-            KeyPressed(Key::Space) => {
-                model.jellies.push(Jelly::default());
+    if let Event::WindowEvent {
+        simple: Some(event),
+        ..
+    } = event
+    {
+        match event {
+            KeyPressed(Key::Space) => model.add_jelly(),
+            KeyPressed(Key::Tab) => model.select((model.selected + 1) % model.jellies.len()),
+            KeyPressed(Key::G) => model.next_gait(),
+            KeyPressed(Key::M) => model.seek = !model.seek,
+            KeyPressed(Key::B) => {
+                model.boundary = match model.boundary {
+                    Boundary::Wrap => Boundary::Bounce,
+                    Boundary::Bounce => Boundary::Soft,
+                    Boundary::Soft => Boundary::Wrap,
+                }
+            }
+            KeyPressed(Key::S) => model.recorder.request_svg(),
+            MouseMoved(position) => model.mouse = Some(position),
+            MouseExited => model.mouse = None,
+            MousePressed(MouseButton::Left) => {
+                if let Some(index) = model.mouse.and_then(|mouse| {
+                    model
+                        .jellies
+                        .iter()
+                        .rposition(|jelly| jelly.contains(mouse))
+                }) {
+                    model.select(index);
+                }
             }
             _ => (),
-        },
-        _ => (),
+        }
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-    draw.background().color(BLACK);
-    for jelly in model.jellies.iter() {
-        jelly.draw(&draw);
+impl Sketch for Model {
+    fn advance(&mut self, bounds: Rect, dt: Duration) {
+        let world = World {
+            bounds,
+            boundary: self.boundary,
+            target: self.mouse.filter(|_| self.seek),
+        };
+        for jelly in self.jellies.iter_mut() {
+            jelly.update(&world, dt.as_secs_f32());
+        }
+        let jelly = &self.jellies[self.selected];
+        self.telemetry.push([
+            jelly.velocity.length(),
+            jelly.thrust_force(),
+            jelly.drag_force(),
+        ]);
+    }
+
+    fn scene(&self, bounds: Rect) -> Scene {
+        let mut scene = Scene::new();
+        scene.background(BLACK);
+        for jelly in self.jellies.iter() {
+            jelly.draw(&mut scene);
+        }
+        if self.jellies.len() > 1 {
+            let jelly = &self.jellies[self.selected];
+            scene
+                .ellipse()
+                .xy(jelly.position)
+                .radius(jelly.draw_settings.size * jelly.draw_settings.scale * 1.5)
+                .no_fill()
+                .stroke(GRAY)
+                .stroke_weight(1.0);
+        }
+
+        // telemetry graph
+        let telemetry_graph_rect = Rect::from_w_h(236.0, 200.0).bottom_left_of(bounds.pad(20.0));
+        self.telemetry.draw(&mut scene, telemetry_graph_rect);
+
+        // direction graph
+        let direction_graph_rect = Rect::from_w_h(200.0, 200.0)
+            .right_of(telemetry_graph_rect)
+            .align_top_of(telemetry_graph_rect);
+        scene
+            .arrow()
+            .start(direction_graph_rect.xy())
+            .end(direction_graph_rect.xy() + self.jellies[self.selected].heading() * 80.0)
+            .color(GRAY)
+            .weight(1.0);
+
+        // gait graph
+        let gait_graph_rect = Rect::from_w_h(200.0, 200.0)
+            .right_of(direction_graph_rect)
+            .align_top_of(direction_graph_rect);
+        self.draw_gait(&mut scene, gait_graph_rect);
+
+        scene
     }
+}
 
-    let win = app.window_rect();
-    
-    // velocity graph
-    let velocity_graph_rect = Rect::from_w_h(200.0, 200.0)
-        .bottom_left_of(win.pad(20.0));
-    let mut points: Vec<Vec2> = Vec::new();
-    let max_y = model
-        .jelly_positions
-        .iter()
-        .fold(0.0, |acc, &x| acc.max(x));
-    for (i, pos) in model.jelly_positions.iter().enumerate() {
-        let x = map_range(
-            i,
-            0,
-            100,
-            velocity_graph_rect.left(),
-            velocity_graph_rect.right(),
-        );
-        let y = map_range(
-            pos.to_owned(),
-            0.0,
-            max_y,
-            velocity_graph_rect.bottom(),
-            velocity_graph_rect.top(),
-        );
-        points.push(pt2(x, y));
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    let scene = model.scene(frame.rect());
+    scene.draw(&draw);
+    if let Err(e) = model.recorder.save_svg(&scene, frame.rect()) {
+        eprintln!("error: {}", e);
     }
-    draw.line()
-        .start(velocity_graph_rect.bottom_left())
-        .end(velocity_graph_rect.top_left())
-        .weight(1.0)
-        .color(GRAY);
-    draw.polyline().weight(2.0).points(points).color(RED);
-    draw.rect()
-        .no_fill()
-        .xy(velocity_graph_rect.xy())
-        .wh(velocity_graph_rect.wh())
-        .stroke(WHITE)
-        .stroke_weight(1.0);
-
-    // direction graph
-    let direction_graph_rect = Rect::from_w_h(200.0, 200.0)
-        .right_of(velocity_graph_rect)
-        .align_top_of(velocity_graph_rect);
-    draw.arrow()
-        .start(direction_graph_rect.xy())
-        .end(direction_graph_rect.xy() + model.jellies[0].velocity.normalize() * 80.0)
-        .color(GRAY)
-        .weight(1.0);
-    
     draw.to_frame(app, &frame).unwrap();
 }
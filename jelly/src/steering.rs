@@ -0,0 +1,182 @@
+use nannou::{
+    noise::{NoiseFn, Seedable, SuperSimplex},
+    prelude::*,
+};
+use rand::Rng;
+
+/// How fast a jelly can turn, in radians per second
+const MAX_TURN: f32 = 1.5;
+/// How fast the noise a jelly wanders by changes, per second
+const WANDER_RATE: f64 = 0.3;
+/// How hard a soft wall pushes a jelly back once it reaches the edge of the world
+const SOFT_WALL: f32 = 200.0;
+
+/// What happens to a jelly at the edge of the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Boundary {
+    /// It leaves one edge and comes back in at the opposite one
+    Wrap,
+    /// It bounces off the edge
+    Bounce,
+    /// The water near the edge pushes it back, harder the closer it gets
+    Soft,
+}
+
+/// Where the jellies are swimming
+#[derive(Debug, Clone, Copy)]
+pub struct World {
+    pub bounds: Rect,
+    pub boundary: Boundary,
+    /// Where the jellies are swimming towards, if anywhere
+    pub target: Option<Point2>,
+}
+
+/// How a jelly picks which way to face. Each behaviour asks for a turn, in radians per second,
+/// and the jelly turns by all of them together, as fast as it can, while its bell relaxes.
+pub struct Steering {
+    /// How hard the jelly turns this way and that at random as it wanders
+    pub wander: f32,
+    /// How hard the jelly turns towards the target, for each radian it is facing away from it
+    pub seek: f32,
+    /// How hard the jelly turns away from the edges, for each radian it is facing into them
+    pub avoid: f32,
+    /// How close to an edge the jelly starts turning away from it (or being pushed back by a
+    /// soft wall)
+    pub edge_distance: f32,
+    noise: SuperSimplex,
+    /// How far through its noise the jelly has wandered
+    wandered: f64,
+}
+
+impl Steering {
+    /// Steering with its own noise to wander by
+    pub fn new(rng: &mut impl Rng) -> Self {
+        Steering {
+            wander: 1.0,
+            seek: 1.0,
+            avoid: 2.0,
+            edge_distance: 150.0,
+            noise: SuperSimplex::new().set_seed(rng.gen()),
+            wandered: 0.0,
+        }
+    }
+
+    /// How fast a jelly at `position` facing `heading` wants to turn, anticlockwise in radians per
+    /// second, after `dt` more seconds of wandering
+    pub fn turn(&mut self, world: &World, position: Point2, heading: Vec2, dt: f32) -> f32 {
+        self.wandered += dt as f64 * WANDER_RATE;
+        let mut turn = self.noise.get([self.wandered, 0.5]) as f32 * self.wander;
+        if let Some(target) = world.target {
+            turn += heading.angle_between(target - position) * self.seek;
+        }
+        if world.boundary != Boundary::Wrap {
+            let away = self.edge_push(world.bounds, position);
+            if away != Vec2::ZERO {
+                turn += heading.angle_between(away) * away.length().min(1.0) * self.avoid;
+            }
+        }
+        turn.clamp(-MAX_TURN, MAX_TURN)
+    }
+
+    /// A push away from each edge of `bounds` that `position` is within `edge_distance` of, from
+    /// nothing at that distance up to 1 at the edge (and more beyond it)
+    pub fn edge_push(&self, bounds: Rect, position: Point2) -> Vec2 {
+        let push = |distance: f32| (1.0 - distance / self.edge_distance).max(0.0);
+        vec2(
+            push(position.x - bounds.left()) - push(bounds.right() - position.x),
+            push(position.y - bounds.bottom()) - push(bounds.top() - position.y),
+        )
+    }
+
+    /// The acceleration a soft wall gives a jelly at `position`
+    pub fn soft_wall(&self, bounds: Rect, position: Point2) -> Vec2 {
+        let push = self.edge_push(bounds, position);
+        push * push.abs() * SOFT_WALL
+    }
+}
+
+impl Boundary {
+    /// Keep a jelly at `position`, moving at `velocity`, within `bounds`, letting it get `margin`
+    /// past the edges before it wraps
+    pub fn contain(self, bounds: Rect, margin: f32, position: &mut Point2, velocity: &mut Vec2) {
+        match self {
+            Boundary::Wrap => {
+                let outer = bounds.pad(-margin);
+                if position.x < outer.left() {
+                    position.x += outer.w();
+                } else if position.x > outer.right() {
+                    position.x -= outer.w();
+                }
+                if position.y < outer.bottom() {
+                    position.y += outer.h();
+                } else if position.y > outer.top() {
+                    position.y -= outer.h();
+                }
+            }
+            Boundary::Bounce => {
+                if position.x < bounds.left() || position.x > bounds.right() {
+                    position.x = position.x.clamp(bounds.left(), bounds.right());
+                    velocity.x = -velocity.x;
+                }
+                if position.y < bounds.bottom() || position.y > bounds.top() {
+                    position.y = position.y.clamp(bounds.bottom(), bounds.top());
+                    velocity.y = -velocity.y;
+                }
+            }
+            Boundary::Soft => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARGIN: f32 = 10.0;
+
+    /// Contain a jelly at `position` moving at `velocity` in a 100 by 100 world about the origin
+    fn contain(boundary: Boundary, position: Point2, velocity: Vec2) -> (Point2, Vec2) {
+        let (mut position, mut velocity) = (position, velocity);
+        boundary.contain(
+            Rect::from_w_h(100.0, 100.0),
+            MARGIN,
+            &mut position,
+            &mut velocity,
+        );
+        (position, velocity)
+    }
+
+    #[test]
+    fn wrapping_moves_a_jelly_past_the_margin_to_the_other_side() {
+        let velocity = vec2(3.0, -4.0);
+        assert_eq!(
+            contain(Boundary::Wrap, pt2(61.0, -62.0), velocity),
+            (pt2(-59.0, 58.0), velocity)
+        );
+        assert_eq!(
+            contain(Boundary::Wrap, pt2(-61.0, 62.0), velocity),
+            (pt2(59.0, -58.0), velocity)
+        );
+        // Within the margin it is left where it is
+        assert_eq!(
+            contain(Boundary::Wrap, pt2(55.0, -55.0), velocity),
+            (pt2(55.0, -55.0), velocity)
+        );
+    }
+
+    #[test]
+    fn bouncing_keeps_a_jelly_in_and_turns_it_back() {
+        assert_eq!(
+            contain(Boundary::Bounce, pt2(60.0, -70.0), vec2(3.0, -4.0)),
+            (pt2(50.0, -50.0), vec2(-3.0, 4.0))
+        );
+        assert_eq!(
+            contain(Boundary::Bounce, pt2(-60.0, 20.0), vec2(-3.0, -4.0)),
+            (pt2(-50.0, 20.0), vec2(3.0, -4.0))
+        );
+        assert_eq!(
+            contain(Boundary::Bounce, pt2(20.0, 30.0), vec2(3.0, -4.0)),
+            (pt2(20.0, 30.0), vec2(3.0, -4.0))
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use nannou::prelude::*;
+
+/// How many segments each tentacle is made of
+const SEGMENTS: usize = 12;
+/// How much of its speed a point of a tentacle loses to the water each second
+const WATER_DRAG: f32 = 3.0;
+/// How many times each update the segments are pulled back to their length
+const ITERATIONS: usize = 4;
+/// How fast the tentacles sway, in radians per second
+const SWAY_SPEED: f32 = 2.5;
+/// How hard the tip of a tentacle is pushed from side to side as it sways
+const SWAY_STRENGTH: f32 = 250.0;
+/// How far behind each point of a tentacle sways after the one before it, in radians
+const SWAY_LAG: f32 = 0.5;
+
+/// A rope hanging from the rim of a jelly's bell, moved with verlet integration: each point keeps
+/// going the way it was going, slowed by the water, and is then pulled back into line with its
+/// neighbours.
+#[derive(Debug, Clone)]
+pub struct Tentacle {
+    /// The points along the tentacle, starting where it hangs from the bell
+    points: Vec<Point2>,
+    /// Where each point was before the last update
+    previous: Vec<Point2>,
+    /// How far apart neighbouring points are kept
+    segment: f32,
+    /// How far through its sway the tentacle is, in radians
+    sway: f32,
+}
+
+impl Tentacle {
+    /// A still tentacle `length` long, hanging straight from `anchor` towards `direction`
+    pub fn new(anchor: Point2, direction: Vec2, length: f32, sway: f32) -> Self {
+        let segment = length / SEGMENTS as f32;
+        let points: Vec<Point2> = (0..=SEGMENTS)
+            .map(|i| anchor + direction * segment * i as f32)
+            .collect();
+        Tentacle {
+            previous: points.clone(),
+            points,
+            segment,
+            sway,
+        }
+    }
+
+    pub fn points(&self) -> &[Point2] {
+        &self.points
+    }
+
+    /// Move the whole tentacle by `offset`, as if it had always been there
+    pub fn shift(&mut self, offset: Vec2) {
+        for point in self.points.iter_mut().chain(&mut self.previous) {
+            *point += offset;
+        }
+    }
+
+    /// Move the tentacle on by `dt` seconds, hanging from `anchor` on a bell facing `heading`
+    pub fn update(&mut self, anchor: Point2, heading: Vec2, dt: f32) {
+        self.sway += SWAY_SPEED * dt;
+        let across = heading.perp();
+        let keep = (1.0 - WATER_DRAG * dt).max(0.0);
+        for i in 1..self.points.len() {
+            let point = self.points[i];
+            let along = i as f32 / SEGMENTS as f32;
+            let sway = (self.sway - i as f32 * SWAY_LAG).sin() * SWAY_STRENGTH * along;
+            self.points[i] = point + (point - self.previous[i]) * keep + across * sway * dt * dt;
+            self.previous[i] = point;
+        }
+        self.points[0] = anchor;
+        self.previous[0] = anchor;
+
+        for _ in 0..ITERATIONS {
+            for i in 1..self.points.len() {
+                let offset = self.points[i] - self.points[i - 1];
+                let length = offset.length();
+                if length <= 0.0 {
+                    continue;
+                }
+                let correction = offset * (1.0 - self.segment / length);
+                // The anchor stays put, so the first point takes the whole correction
+                if i == 1 {
+                    self.points[i] -= correction;
+                } else {
+                    self.points[i - 1] += correction / 2.0;
+                    self.points[i] -= correction / 2.0;
+                }
+            }
+        }
+    }
+}
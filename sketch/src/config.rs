@@ -0,0 +1,127 @@
+//! Loading and saving the TOML and RON files that sketches are configured with, with errors that
+//! say which value in the file was wrong.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Why a config file could not be loaded or saved
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The file is neither TOML nor RON
+    Format { path: PathBuf },
+    /// The file could not be parsed; `field` is the path to the value that was wrong
+    Parse {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+    /// The file parsed, but a value in it does not make sense
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+    /// The file could not be written
+    Save { path: PathBuf, message: String },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            ConfigError::Format { path } => write!(
+                f,
+                "could not read {}: expected a .toml or .ron file",
+                path.display()
+            ),
+            ConfigError::Parse {
+                path,
+                field,
+                message,
+            } => write!(
+                f,
+                "invalid config {}: in `{}`: {}",
+                path.display(),
+                field,
+                message.trim_end()
+            ),
+            ConfigError::Invalid {
+                path,
+                field,
+                message,
+            } => write!(
+                f,
+                "invalid config {}: `{}` {}",
+                path.display(),
+                field,
+                message
+            ),
+            ConfigError::Save { path, message } => {
+                write!(f, "could not save {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load a `.toml` or `.ron` file
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let parse_error = |field: String, message: String| ConfigError::Parse {
+        path: path.to_owned(),
+        field,
+        message,
+    };
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => serde_path_to_error::deserialize(toml::Deserializer::new(&text))
+            .map_err(|e| parse_error(e.path().to_string(), e.inner().message().to_owned())),
+        Some("ron") => {
+            let mut deserializer = ron::Deserializer::from_str(&text)
+                .map_err(|e| parse_error(".".to_owned(), e.to_string()))?;
+            serde_path_to_error::deserialize(&mut deserializer)
+                .map_err(|e| parse_error(e.path().to_string(), e.inner().to_string()))
+        }
+        _ => Err(ConfigError::Format {
+            path: path.to_owned(),
+        }),
+    }
+}
+
+/// Save `value` to a `.toml` or `.ron` file
+pub fn save<T: Serialize>(value: &T, path: &Path) -> Result<(), ConfigError> {
+    let save_error = |message: String| ConfigError::Save {
+        path: path.to_owned(),
+        message,
+    };
+    let text = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::to_string(value).map_err(|e| save_error(e.to_string()))?,
+        Some("ron") => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+            .map_err(|e| save_error(e.to_string()))?,
+        _ => {
+            return Err(ConfigError::Format {
+                path: path.to_owned(),
+            })
+        }
+    };
+    fs::write(path, text).map_err(|e| save_error(e.to_string()))
+}
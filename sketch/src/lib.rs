@@ -0,0 +1,40 @@
+//! Pieces shared between the sketches in this repository
+
+pub mod config;
+pub mod plot;
+pub mod raster;
+pub mod record;
+pub mod scene;
+pub mod seed;
+pub mod serialize;
+pub mod svg;
+pub mod timestep;
+
+use nannou::geom::Rect;
+use scene::Scene;
+use std::{str::FromStr, time::Duration};
+
+/// A sketch that can be advanced and drawn without a nannou `App`, so that it can be recorded
+/// without a window
+pub trait Sketch {
+    /// Advance the sketch by `dt`, in a window covering `bounds`
+    fn advance(&mut self, bounds: Rect, dt: Duration);
+
+    /// What the sketch looks like now, in a window covering `bounds`
+    fn scene(&self, bounds: Rect) -> Scene;
+}
+
+/// Parse a number for a command line argument that must be finite and more than zero, such as a
+/// rate or a period
+pub fn parse_positive<T: FromStr + Into<f64> + Copy>(text: &str) -> Result<T, String> {
+    let value: T = text
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", text))?;
+    let number: f64 = value.into();
+    if number.is_finite() && number > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("must be more than zero, not `{}`", text))
+    }
+}
@@ -0,0 +1,372 @@
+//! A graph of how values change over time, for sketches to show what their simulations are
+//! doing.
+//!
+//! A [`Plot`] keeps the last few samples of each of its series, and draws them into a [`Scene`]
+//! with a legend and a labelled y axis.
+
+use crate::scene::{Color, Scene};
+use nannou::color::{IntoLinSrgba, GRAY, WHITE};
+use nannou::geom::{pt2, Rect};
+use nannou::math::map_range;
+use std::collections::VecDeque;
+
+/// How wide the gutter for the y axis labels is
+const GUTTER: f32 = 36.0;
+const FONT_SIZE: u32 = 10;
+/// How tall each line of the legend is
+const LEGEND_LINE: f32 = 14.0;
+
+/// The values a plot's y axis covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YRange {
+    /// Just wide enough for every sample, rounded out to the next ticks
+    Auto,
+    /// Wide enough for every sample and zero
+    AutoFromZero,
+    Fixed {
+        min: f32,
+        max: f32,
+    },
+}
+
+/// One named line on a plot
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    pub color: Color,
+    /// The samples, oldest first
+    samples: VecDeque<f32>,
+}
+
+/// Several series of samples over time, each keeping only its most recent samples
+#[derive(Debug, Clone)]
+pub struct Plot {
+    pub series: Vec<Series>,
+    /// How many samples each series keeps
+    capacity: usize,
+    pub y_range: YRange,
+    /// Roughly how many ticks to label on the y axis
+    pub ticks: usize,
+}
+
+impl Plot {
+    /// An empty plot keeping the last `capacity` samples of each series
+    pub fn new(capacity: usize) -> Self {
+        Plot {
+            series: vec![],
+            capacity: capacity.max(2),
+            y_range: YRange::Auto,
+            ticks: 4,
+        }
+    }
+
+    /// Add a series named `name`, drawn in `color`
+    pub fn series(mut self, name: &str, color: impl IntoLinSrgba<f32>) -> Self {
+        self.series.push(Series {
+            name: name.to_owned(),
+            color: color.into_lin_srgba(),
+            samples: VecDeque::with_capacity(self.capacity),
+        });
+        self
+    }
+
+    pub fn y_range(mut self, y_range: YRange) -> Self {
+        self.y_range = y_range;
+        self
+    }
+
+    /// Add the next sample of each series, in the order the series were added, dropping the
+    /// oldest samples once there are too many
+    pub fn push(&mut self, values: impl IntoIterator<Item = f32>) {
+        for (series, value) in self.series.iter_mut().zip(values) {
+            if series.samples.len() == self.capacity {
+                series.samples.pop_front();
+            }
+            series.samples.push_back(value);
+        }
+    }
+
+    /// Forget every sample, such as when the plot starts following something else
+    pub fn clear(&mut self) {
+        for series in &mut self.series {
+            series.samples.clear();
+        }
+    }
+
+    /// The bottom and top of the y axis, and the distance between its ticks
+    fn axis(&self) -> (f32, f32, f32) {
+        let samples = || {
+            self.series
+                .iter()
+                .flat_map(|series| series.samples.iter().copied())
+                .filter(|sample| sample.is_finite())
+        };
+        let (min, max) = match self.y_range {
+            YRange::Fixed { min, max } => (min, max),
+            YRange::Auto | YRange::AutoFromZero => {
+                let start = match self.y_range {
+                    YRange::AutoFromZero => (0.0, 0.0),
+                    _ => (f32::INFINITY, f32::NEG_INFINITY),
+                };
+                samples().fold(start, |(min, max), sample| {
+                    (min.min(sample), max.max(sample))
+                })
+            }
+        };
+        let (min, max) = match (min.is_finite() && max.is_finite(), min < max) {
+            (false, _) => (0.0, 1.0),
+            // Widened enough to still be a range once rounded to an f32, however big the values
+            (true, false) => {
+                let pad = (min.abs().max(max.abs()) * 1e-3).max(1.0);
+                (min - pad, max + pad)
+            }
+            (true, true) => (min, max),
+        };
+        let step = tick_step((max - min) / self.ticks.max(1) as f32);
+        match self.y_range {
+            YRange::Fixed { .. } => (min, max, step),
+            _ => (
+                (min / step).floor() * step,
+                (max / step).ceil() * step,
+                step,
+            ),
+        }
+    }
+
+    /// Draw the plot filling `rect`: the y axis labels down the left, the series as lines from
+    /// the oldest sample on the left to the newest on the right, and the legend in the top left
+    pub fn draw(&self, scene: &mut Scene, rect: Rect) {
+        let (bottom, top, step) = self.axis();
+        let area = Rect::from_corners(pt2(rect.left() + GUTTER, rect.bottom()), rect.top_right());
+        let y = |value: f32| map_range(value, bottom, top, area.bottom(), area.top());
+
+        // Ticks, from the first multiple of the step on the axis
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+        for tick in ticks(bottom, top, step) {
+            // Don't label zero as -0 when it is reached from below
+            let tick_value = if tick.abs() < step * 1e-3 { 0.0 } else { tick };
+            let height = y(tick);
+            scene
+                .line()
+                .start(pt2(area.left(), height))
+                .end(pt2(area.right(), height))
+                .weight(1.0)
+                .color(Color::new(0.05, 0.05, 0.05, 1.0));
+            let label = Rect::from_x_y_w_h(
+                rect.left() + GUTTER / 2.0 - 2.0,
+                height,
+                GUTTER - 4.0,
+                LEGEND_LINE,
+            );
+            scene
+                .text(&format!("{:.*}", decimals, tick_value))
+                .xy(label.xy())
+                .wh(label.wh())
+                .font_size(FONT_SIZE)
+                .right_justify()
+                .color(GRAY);
+        }
+
+        for series in &self.series {
+            // Samples that aren't finite have nowhere to go, so they are left out
+            let points = series
+                .samples
+                .iter()
+                .enumerate()
+                .filter(|(_, sample)| sample.is_finite())
+                .map(|(i, &sample)| {
+                    pt2(
+                        map_range(
+                            i as f32,
+                            0.0,
+                            (self.capacity - 1) as f32,
+                            area.left(),
+                            area.right(),
+                        ),
+                        y(sample.clamp(bottom, top)),
+                    )
+                });
+            scene
+                .polyline()
+                .weight(2.0)
+                .points(points)
+                .color(series.color);
+        }
+
+        scene
+            .rect()
+            .no_fill()
+            .xy(area.xy())
+            .wh(area.wh())
+            .stroke(WHITE)
+            .stroke_weight(1.0);
+
+        for (i, series) in self.series.iter().enumerate() {
+            let line = Rect::from_w_h(area.w() - 8.0, LEGEND_LINE)
+                .top_left_of(area.pad(4.0))
+                .shift_y(-(i as f32) * LEGEND_LINE);
+            let swatch = Rect::from_w_h(8.0, 8.0).mid_left_of(line);
+            scene
+                .rect()
+                .xy(swatch.xy())
+                .wh(swatch.wh())
+                .color(series.color);
+            let text = line.pad_left(12.0);
+            scene
+                .text(&series.name)
+                .xy(text.xy())
+                .wh(text.wh())
+                .font_size(FONT_SIZE)
+                .left_justify()
+                .color(WHITE);
+        }
+    }
+}
+
+/// Each multiple of `step` from `bottom` to `top`.
+///
+/// The ticks are counted out rather than added up, so that a step too small to change a large
+/// `f32` can't keep the ticks from ever reaching the top.
+fn ticks(bottom: f32, top: f32, step: f32) -> impl Iterator<Item = f32> {
+    let (bottom, top, step) = (bottom as f64, top as f64, step as f64);
+    let first = (bottom / step).ceil();
+    let last = (top / step + 1e-3).floor();
+    let count = if last >= first {
+        (last - first) as usize + 1
+    } else {
+        0
+    };
+    (0..count).map(move |i| ((first + i as f64) * step) as f32)
+}
+
+/// A round distance between ticks close to `rough`: 1, 2 or 5 times a power of ten
+fn tick_step(rough: f32) -> f32 {
+    if rough <= 0.0 || !rough.is_finite() {
+        return 1.0;
+    }
+    let power = 10f32.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|multiple| multiple * power)
+        .find(|&step| step >= rough)
+        .unwrap_or(10.0 * power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::color::{BLUE, RED};
+
+    fn plot(y_range: YRange, samples: &[f32]) -> Plot {
+        let mut plot = Plot::new(samples.len()).series("a", RED).y_range(y_range);
+        for &sample in samples {
+            plot.push([sample]);
+        }
+        plot
+    }
+
+    fn samples(series: &Series) -> Vec<f32> {
+        series.samples.iter().copied().collect()
+    }
+
+    #[test]
+    fn tick_steps_are_round_numbers() {
+        for (rough, step) in [
+            (0.3, 0.5),
+            (1.0, 1.0),
+            (1.1, 2.0),
+            (3.0, 5.0),
+            (7.0, 10.0),
+            (0.012, 0.02),
+            (450.0, 500.0),
+        ] {
+            assert!(
+                (tick_step(rough) - step).abs() < step * 1e-5,
+                "{} gave {}",
+                rough,
+                tick_step(rough)
+            );
+        }
+        for rough in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(tick_step(rough), 1.0);
+        }
+    }
+
+    #[test]
+    fn automatic_axes_round_out_to_the_ticks() {
+        assert_eq!(plot(YRange::Auto, &[2.3, 7.9]).axis(), (2.0, 8.0, 2.0));
+        assert_eq!(
+            plot(YRange::AutoFromZero, &[2.3, 7.9]).axis(),
+            (0.0, 8.0, 2.0)
+        );
+        assert_eq!(
+            plot(YRange::AutoFromZero, &[-2.3, -7.9]).axis(),
+            (-8.0, 0.0, 2.0)
+        );
+        // Samples that aren't finite are left out
+        assert_eq!(
+            plot(YRange::Auto, &[2.3, f32::NAN, 7.9, f32::INFINITY]).axis(),
+            (2.0, 8.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn fixed_axes_keep_their_range() {
+        let y_range = YRange::Fixed {
+            min: -1.0,
+            max: 1.0,
+        };
+        assert_eq!(plot(y_range, &[5.0, -5.0]).axis(), (-1.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn axes_without_a_range_of_samples_still_have_one() {
+        assert_eq!(Plot::new(10).series("a", RED).axis(), (0.0, 1.0, 0.5));
+        assert_eq!(plot(YRange::Auto, &[f32::NAN]).axis(), (0.0, 1.0, 0.5));
+        assert_eq!(plot(YRange::Auto, &[5.0]).axis(), (4.0, 6.0, 0.5));
+        assert_eq!(plot(YRange::Auto, &[5.0, 5.0]).axis(), (4.0, 6.0, 0.5));
+
+        let (bottom, top, step) = plot(YRange::Auto, &[3e7]).axis();
+        assert!(bottom < 3e7 && 3e7 < top, "{} to {}", bottom, top);
+        assert!(ticks(bottom, top, step).count() <= 10);
+    }
+
+    #[test]
+    fn ticks_are_counted_from_bottom_to_top() {
+        assert_eq!(
+            ticks(0.0, 8.0, 2.0).collect::<Vec<_>>(),
+            [0.0, 2.0, 4.0, 6.0, 8.0]
+        );
+        assert_eq!(ticks(-1.0, 1.0, 0.4).count(), 5);
+        assert_eq!(ticks(1.0, -1.0, 0.5).count(), 0);
+        // A step too small to change the values still ends
+        assert!(ticks(3e7 - 2.0, 3e7 + 2.0, 0.5).count() <= 9);
+    }
+
+    #[test]
+    fn pushing_drops_the_oldest_samples() {
+        let mut plot = Plot::new(3).series("a", RED).series("b", BLUE);
+        for i in 0..5 {
+            plot.push([i as f32, -i as f32]);
+        }
+        assert_eq!(samples(&plot.series[0]), [2.0, 3.0, 4.0]);
+        assert_eq!(samples(&plot.series[1]), [-2.0, -3.0, -4.0]);
+
+        // Series without a value this time are left as they were
+        plot.push([5.0]);
+        assert_eq!(samples(&plot.series[0]), [3.0, 4.0, 5.0]);
+        assert_eq!(samples(&plot.series[1]), [-2.0, -3.0, -4.0]);
+
+        plot.clear();
+        assert!(samples(&plot.series[0]).is_empty());
+    }
+
+    #[test]
+    fn plots_keep_at_least_two_samples() {
+        let mut plot = Plot::new(0).series("a", RED);
+        for i in 0..4 {
+            plot.push([i as f32]);
+        }
+        assert_eq!(samples(&plot.series[0]), [2.0, 3.0]);
+    }
+}
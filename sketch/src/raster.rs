@@ -0,0 +1,243 @@
+//! Drawing a [`Scene`] into an image on the CPU, for recording without a window or a GPU
+
+use crate::scene::{srgba, Color, Scene, Shape};
+use nannou::geom::Point2;
+use nannou::lyon::path::PathEvent;
+use std::{io, path::Path};
+use tiny_skia::{FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+/// An image that scenes are drawn into.
+///
+/// The image is kept between scenes, so that scenes without a background are drawn over the
+/// last one, as they are in a nannou window.
+pub struct Raster {
+    pixmap: Pixmap,
+}
+
+impl Raster {
+    /// A `width` by `height` image, starting out black
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut pixmap = Pixmap::new(width, height).expect("frame size must not be zero");
+        pixmap.fill(tiny_skia::Color::BLACK);
+        Raster { pixmap }
+    }
+
+    /// Nannou's coordinates (origin in the centre, y up) to the image's (origin top left, y down)
+    fn transform(&self) -> Transform {
+        Transform::from_row(
+            1.0,
+            0.0,
+            0.0,
+            -1.0,
+            self.pixmap.width() as f32 / 2.0,
+            self.pixmap.height() as f32 / 2.0,
+        )
+    }
+
+    /// The transform for a shape at `position`, turned by `rotation` radians
+    fn placed(&self, position: Point2, rotation: f32) -> Transform {
+        self.transform()
+            .pre_translate(position.x, position.y)
+            .pre_rotate(rotation.to_degrees())
+    }
+
+    pub fn draw(&mut self, scene: &Scene) {
+        if let Some(background) = scene.background {
+            self.pixmap.fill(color(background));
+        }
+        for shape in &scene.shapes {
+            match shape {
+                Shape::Polygon(polygon) => {
+                    let transform = self.placed(polygon.position, polygon.rotation);
+                    if let Some(path) = polyline_path(&polygon.points, true) {
+                        self.fill_and_stroke(
+                            &path,
+                            polygon.fill,
+                            polygon.stroke,
+                            polygon.stroke_weight,
+                            transform,
+                        );
+                    }
+                }
+                Shape::Ellipse(ellipse) => {
+                    let transform = self.placed(ellipse.position, ellipse.rotation);
+                    let (w, h) = (ellipse.size.x, ellipse.size.y);
+                    let oval = tiny_skia::Rect::from_xywh(-w / 2.0, -h / 2.0, w, h);
+                    if let Some(path) = oval.and_then(PathBuilder::from_oval) {
+                        self.fill_and_stroke(
+                            &path,
+                            ellipse.fill,
+                            ellipse.stroke,
+                            ellipse.stroke_weight,
+                            transform,
+                        );
+                    }
+                }
+                Shape::Rectangle(rect) => {
+                    let transform = self.placed(rect.position, rect.rotation);
+                    let (w, h) = (rect.size.x, rect.size.y);
+                    if let Some(bounds) = tiny_skia::Rect::from_xywh(-w / 2.0, -h / 2.0, w, h) {
+                        self.fill_and_stroke(
+                            &PathBuilder::from_rect(bounds),
+                            rect.fill,
+                            rect.stroke,
+                            rect.stroke_weight,
+                            transform,
+                        );
+                    }
+                }
+                Shape::Line(line) => {
+                    let cap = if line.round_caps {
+                        LineCap::Round
+                    } else {
+                        LineCap::Butt
+                    };
+                    self.stroke_line(&[line.start, line.end], line.color, line.weight, cap);
+                }
+                Shape::Polyline(polyline) => {
+                    self.stroke_line(
+                        &polyline.points,
+                        polyline.color,
+                        polyline.weight,
+                        LineCap::Butt,
+                    );
+                }
+                Shape::Arrow(arrow) => {
+                    let (line, head) = arrow.geometry();
+                    if let Some((start, end)) = line {
+                        let cap = if arrow.round_start {
+                            LineCap::Round
+                        } else {
+                            LineCap::Butt
+                        };
+                        self.stroke_line(&[start, end], arrow.color, arrow.weight, cap);
+                    }
+                    if let Some(path) = polyline_path(&head, true) {
+                        self.fill_and_stroke(&path, Some(arrow.color), None, 0.0, self.transform());
+                    }
+                }
+                Shape::Text(text) => {
+                    let mut builder = PathBuilder::new();
+                    for event in text.layout().path_events() {
+                        match event {
+                            PathEvent::Begin { at } => builder.move_to(at.x, at.y),
+                            PathEvent::Line { to, .. } => builder.line_to(to.x, to.y),
+                            PathEvent::Quadratic { ctrl, to, .. } => {
+                                builder.quad_to(ctrl.x, ctrl.y, to.x, to.y)
+                            }
+                            PathEvent::Cubic {
+                                ctrl1, ctrl2, to, ..
+                            } => builder.cubic_to(ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y),
+                            PathEvent::End { close: true, .. } => builder.close(),
+                            PathEvent::End { close: false, .. } => (),
+                        }
+                    }
+                    if let Some(path) = builder.finish() {
+                        self.pixmap.fill_path(
+                            &path,
+                            &paint(text.color),
+                            FillRule::Winding,
+                            self.placed(text.position, text.rotation),
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn fill_and_stroke(
+        &mut self,
+        path: &tiny_skia::Path,
+        fill: Option<Color>,
+        stroke: Option<Color>,
+        stroke_weight: f32,
+        transform: Transform,
+    ) {
+        if let Some(fill) = fill {
+            self.pixmap
+                .fill_path(path, &paint(fill), FillRule::EvenOdd, transform, None);
+        }
+        if let Some(stroke) = stroke {
+            let style = Stroke {
+                width: stroke_weight,
+                line_join: LineJoin::Miter,
+                ..Stroke::default()
+            };
+            self.pixmap
+                .stroke_path(path, &paint(stroke), &style, transform, None);
+        }
+    }
+
+    fn stroke_line(&mut self, points: &[Point2], color: Color, weight: f32, cap: LineCap) {
+        if let Some(path) = polyline_path(points, false) {
+            let style = Stroke {
+                width: weight,
+                line_cap: cap,
+                line_join: LineJoin::Round,
+                ..Stroke::default()
+            };
+            self.pixmap
+                .stroke_path(&path, &paint(color), &style, self.transform(), None);
+        }
+    }
+
+    /// Write the image to a PNG file
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        self.pixmap
+            .save_png(path)
+            .map_err(|e| io::Error::other(format!("could not write {}: {}", path.display(), e)))
+    }
+}
+
+fn polyline_path(points: &[Point2], closed: bool) -> Option<tiny_skia::Path> {
+    let (first, rest) = points.split_first()?;
+    let mut builder = PathBuilder::new();
+    builder.move_to(first.x, first.y);
+    for point in rest {
+        builder.line_to(point.x, point.y);
+    }
+    if closed {
+        builder.close();
+    }
+    builder.finish()
+}
+
+fn color(color: Color) -> tiny_skia::Color {
+    let srgba = srgba(color);
+    tiny_skia::Color::from_rgba(srgba.red, srgba.green, srgba.blue, srgba.alpha)
+        .unwrap_or(tiny_skia::Color::BLACK)
+}
+
+fn paint(c: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color(c));
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::color::{BLUE, RED};
+
+    /// The colour of the pixel at `x`, `y` from the top left, as RGBA bytes
+    fn pixel(raster: &Raster, x: u32, y: u32) -> [u8; 4] {
+        let pixel = raster.pixmap.pixel(x, y).unwrap().demultiply();
+        [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+    }
+
+    #[test]
+    fn shapes_are_drawn_where_nannou_would_draw_them() {
+        let mut scene = Scene::new();
+        scene.background(BLUE);
+        // Right of the centre and up, which is towards the top of the image
+        scene.rect().x_y(5.0, 4.0).w_h(6.0, 6.0).color(RED);
+        let mut raster = Raster::new(20, 20);
+        raster.draw(&scene);
+
+        assert_eq!(pixel(&raster, 15, 6), [255, 0, 0, 255]);
+        assert_eq!(pixel(&raster, 5, 6), [0, 0, 255, 255]);
+        assert_eq!(pixel(&raster, 15, 14), [0, 0, 255, 255]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Recording a sketch to numbered PNG frames or an SVG, with or without a window.
+//!
+//! While recording, or when a number of frames is given, every frame advances the simulation by
+//! the same amount of simulated time (`1 / --fps` seconds), however long it took to draw, so a
+//! recording plays back smoothly at that rate.
+
+use crate::{raster::Raster, scene::Scene, svg, Sketch};
+use nannou::{geom::Rect, App};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+/// The recording command line arguments, to flatten into a sketch's arguments
+#[derive(Debug, Clone, clap::Args)]
+pub struct RecordArgs {
+    /// Write every frame to a numbered PNG in this directory
+    #[arg(long, value_name = "DIR", group = "output")]
+    pub record: Option<PathBuf>,
+    /// Write a frame to this SVG file: the last one if --frames is given, otherwise the first
+    #[arg(long, value_name = "FILE", group = "output")]
+    pub svg: Option<PathBuf>,
+    /// How many frames make up a second of simulated time while recording
+    #[arg(long, default_value_t = 60.0, value_parser = crate::parse_positive::<f64>)]
+    pub fps: f64,
+    /// Stop (and quit) after this many frames
+    #[arg(long)]
+    pub frames: Option<u64>,
+    /// Draw the frames without opening a window
+    #[arg(long, requires_all = ["output", "frames"])]
+    pub headless: bool,
+    /// The size of the frames drawn without a window, as WIDTHxHEIGHT
+    #[arg(long, default_value = "1024x768", value_parser = parse_size)]
+    pub size: (u32, u32),
+    /// Also encode the frames into this video file with ffmpeg, once recording stops
+    #[arg(long, requires_all = ["record", "frames"])]
+    pub video: Option<PathBuf>,
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let parse = |n: &str| n.trim().parse::<u32>().ok().filter(|&n| n > 0);
+    size.split_once('x')
+        .and_then(|(w, h)| Some((parse(w)?, parse(h)?)))
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, e.g. 1024x768, not `{}`", size))
+}
+
+/// Counts the frames of a sketch, and decides which of them to write out and where
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    dir: Option<PathBuf>,
+    svg: Option<PathBuf>,
+    dt: Duration,
+    fps: f64,
+    frames: Option<u64>,
+    video: Option<PathBuf>,
+    frame: u64,
+    finished: bool,
+    /// Where to write an SVG of the next frame, when asked for with `request_svg`
+    requested_svg: Option<PathBuf>,
+    /// Where to write an SVG of the current frame
+    due_svg: Option<PathBuf>,
+}
+
+impl Default for Recorder {
+    /// A recorder that writes nothing and never stops
+    fn default() -> Self {
+        Recorder {
+            dir: None,
+            svg: None,
+            dt: Duration::from_secs_f64(1.0 / 60.0),
+            fps: 60.0,
+            frames: None,
+            video: None,
+            frame: 0,
+            finished: false,
+            requested_svg: None,
+            due_svg: None,
+        }
+    }
+}
+
+impl Recorder {
+    /// A recorder for the arguments, creating the directory to record into
+    pub fn new(args: &RecordArgs) -> io::Result<Self> {
+        if let Some(dir) = &args.record {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Recorder {
+            dir: args.record.clone(),
+            svg: args.svg.clone(),
+            dt: Duration::from_secs_f64(1.0 / args.fps),
+            fps: args.fps,
+            frames: args.frames,
+            video: args.video.clone(),
+            ..Recorder::default()
+        })
+    }
+
+    /// Whether frames advance by a fixed time rather than the time since the last one
+    fn fixed_time(&self) -> bool {
+        self.dir.is_some() || self.frames.is_some()
+    }
+
+    /// Move on to the next frame, returning `false` once all the frames have been drawn
+    fn next_frame(&mut self) -> bool {
+        if self.frames.is_some_and(|frames| self.frame >= frames) {
+            return false;
+        }
+        self.frame += 1;
+        let svg_frame = self.frames.unwrap_or(1);
+        self.due_svg = self.requested_svg.take().or_else(|| {
+            (self.frame == svg_frame)
+                .then(|| self.svg.clone())
+                .flatten()
+        });
+        true
+    }
+
+    /// Where to write the current frame as a PNG, if recording
+    fn png_path(&self) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(format!("{:05}.png", self.frame - 1)))
+    }
+
+    /// Call from a sketch's `update` to find how far to advance the simulation for the frame
+    /// about to be drawn (`since_last` unless the frames are fixed in time), capturing the
+    /// frame if recording.
+    ///
+    /// Once the last frame is drawn this waits for the captures to be written, encodes the
+    /// video if one was asked for, and quits.
+    pub fn frame_time(&mut self, app: &App, since_last: Duration) -> Duration {
+        if self.finished {
+            return Duration::ZERO;
+        }
+        if !self.next_frame() {
+            self.finished = true;
+            app.main_window().await_capture_frame_jobs().ok();
+            if let Err(e) = self.finish() {
+                eprintln!("error: {}", e);
+            }
+            app.quit();
+            return Duration::ZERO;
+        }
+        if let Some(path) = self.png_path() {
+            app.main_window().capture_frame(path);
+        }
+        if self.fixed_time() {
+            self.dt
+        } else {
+            since_last
+        }
+    }
+
+    /// Write an SVG of the next frame drawn into the working directory, e.g. on a key press
+    pub fn request_svg(&mut self) {
+        self.requested_svg = Some(PathBuf::from(format!("frame-{:05}.svg", self.frame + 1)));
+    }
+
+    /// Call with the scene of each frame as it is drawn, to write it to an SVG if it was asked
+    /// for (by `--svg` or `request_svg`)
+    pub fn save_svg(&self, scene: &Scene, bounds: Rect) -> io::Result<()> {
+        let Some(path) = &self.due_svg else {
+            return Ok(());
+        };
+        svg::save(scene, bounds, path)?;
+        println!("saved {}", path.display());
+        Ok(())
+    }
+
+    /// Encode the recorded frames into a video, if one was asked for
+    pub fn finish(&self) -> io::Result<()> {
+        match (&self.dir, &self.video) {
+            (Some(dir), Some(video)) => encode_video(dir, self.fps, video),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Run `ffmpeg` over the numbered frames in `dir`
+fn encode_video(dir: &Path, fps: f64, video: &Path) -> io::Result<()> {
+    let status = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-framerate"])
+        .arg(fps.to_string())
+        .arg("-i")
+        .arg(dir.join("%05d.png"))
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(video)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("could not run ffmpeg: {}", e)))?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "ffmpeg failed to encode {} ({})",
+            video.display(),
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Record a sketch without a window, rasterising its scenes on the CPU
+pub fn run_headless(sketch: &mut impl Sketch, args: &RecordArgs) -> io::Result<()> {
+    let mut recorder = Recorder::new(args)?;
+    let (width, height) = args.size;
+    let bounds = Rect::from_w_h(width as f32, height as f32);
+    let mut raster = Raster::new(width, height);
+    while recorder.next_frame() {
+        sketch.advance(bounds, recorder.dt);
+        let scene = sketch.scene(bounds);
+        if let Some(path) = recorder.png_path() {
+            raster.draw(&scene);
+            raster.save_png(&path)?;
+        }
+        recorder.save_svg(&scene, bounds)?;
+    }
+    recorder.finish()
+}
@@ -0,0 +1,615 @@
+//! A description of what a sketch draws in a frame, independent of what draws it.
+//!
+//! Sketches build a [`Scene`] with the same builder calls they would make on a nannou `Draw`,
+//! and the scene can then be drawn to a window with [`Scene::draw`], rasterised without one
+//! (see [`crate::raster`]) or written out as an SVG (see [`crate::svg`]).
+
+use nannou::color::{IntoLinSrgba, LinSrgba, Srgba};
+use nannou::geom::{Point2, Rect, Vec2};
+use nannou::text::{Align, FontSize, Justify};
+
+/// The colours in a scene are linear sRGB with alpha, as nannou uses
+pub type Color = LinSrgba;
+
+/// Everything drawn in one frame, in nannou's coordinates (origin in the centre, y up)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scene {
+    /// The colour to clear the frame to; frames without one are drawn over the last frame
+    pub background: Option<Color>,
+    /// The shapes to draw, back to front
+    pub shapes: Vec<Shape>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Polygon(Polygon),
+    Ellipse(Ellipse),
+    Rectangle(Rectangle),
+    Line(Line),
+    Polyline(Polyline),
+    Arrow(Arrow),
+    Text(Text),
+}
+
+/// A colour as sRGB, with every channel clamped to 0..=1.
+///
+/// Nannou blends in linear space, but the sketches choose their colours in sRGB, so this gets
+/// back the colours they asked for.
+pub fn srgba(color: Color) -> Srgba {
+    let srgba = Srgba::from_linear(color);
+    let channel = |c: f32| c.clamp(0.0, 1.0);
+    Srgba::new(
+        channel(srgba.red),
+        channel(srgba.green),
+        channel(srgba.blue),
+        channel(srgba.alpha),
+    )
+}
+
+fn white() -> Color {
+    nannou::color::WHITE.into_lin_srgba()
+}
+
+/// Setters for where a shape is placed and how far it is turned
+macro_rules! placement {
+    ($shape:ty) => {
+        impl $shape {
+            pub fn xy(&mut self, position: Point2) -> &mut Self {
+                self.position = position;
+                self
+            }
+
+            pub fn x_y(&mut self, x: f32, y: f32) -> &mut Self {
+                self.xy(Point2::new(x, y))
+            }
+
+            /// Turn the shape anticlockwise by `rotation` radians about its position
+            pub fn rotate(&mut self, rotation: f32) -> &mut Self {
+                self.rotation = rotation;
+                self
+            }
+        }
+    };
+}
+
+/// Setters for a shape's width and height
+macro_rules! size {
+    ($shape:ty) => {
+        impl $shape {
+            pub fn wh(&mut self, size: Vec2) -> &mut Self {
+                self.size = size;
+                self
+            }
+
+            pub fn w_h(&mut self, w: f32, h: f32) -> &mut Self {
+                self.wh(Vec2::new(w, h))
+            }
+        }
+    };
+}
+
+/// Setters for a closed shape's fill and outline
+macro_rules! fill_and_stroke {
+    ($shape:ty) => {
+        impl $shape {
+            /// The colour to fill the shape with
+            pub fn color(&mut self, color: impl IntoLinSrgba<f32>) -> &mut Self {
+                self.fill = Some(color.into_lin_srgba());
+                self
+            }
+
+            pub fn no_fill(&mut self) -> &mut Self {
+                self.fill = None;
+                self
+            }
+
+            /// The colour to outline the shape with
+            pub fn stroke(&mut self, color: impl IntoLinSrgba<f32>) -> &mut Self {
+                self.stroke = Some(color.into_lin_srgba());
+                self
+            }
+
+            pub fn stroke_color(&mut self, color: impl IntoLinSrgba<f32>) -> &mut Self {
+                self.stroke(color)
+            }
+
+            pub fn stroke_weight(&mut self, weight: f32) -> &mut Self {
+                self.stroke_weight = weight;
+                self
+            }
+        }
+    };
+}
+
+/// Setters for the colour and thickness of a line
+macro_rules! line_style {
+    ($shape:ty) => {
+        impl $shape {
+            pub fn color(&mut self, color: impl IntoLinSrgba<f32>) -> &mut Self {
+                self.color = color.into_lin_srgba();
+                self
+            }
+
+            pub fn weight(&mut self, weight: f32) -> &mut Self {
+                self.weight = weight;
+                self
+            }
+
+            pub fn stroke_weight(&mut self, weight: f32) -> &mut Self {
+                self.weight(weight)
+            }
+        }
+    };
+}
+
+/// A closed polygon, with its points relative to its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<Point2>,
+    pub position: Point2,
+    pub rotation: f32,
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_weight: f32,
+}
+
+impl Default for Polygon {
+    fn default() -> Self {
+        Polygon {
+            points: vec![],
+            position: Point2::ZERO,
+            rotation: 0.0,
+            fill: Some(white()),
+            stroke: None,
+            stroke_weight: 1.0,
+        }
+    }
+}
+
+impl Polygon {
+    pub fn points(&mut self, points: impl IntoIterator<Item = Point2>) -> &mut Self {
+        self.points = points.into_iter().collect();
+        self
+    }
+}
+
+placement!(Polygon);
+fill_and_stroke!(Polygon);
+
+/// An ellipse centred on its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ellipse {
+    pub position: Point2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_weight: f32,
+}
+
+impl Default for Ellipse {
+    fn default() -> Self {
+        Ellipse {
+            position: Point2::ZERO,
+            size: Vec2::splat(100.0),
+            rotation: 0.0,
+            fill: Some(white()),
+            stroke: None,
+            stroke_weight: 1.0,
+        }
+    }
+}
+
+impl Ellipse {
+    /// Make the ellipse a circle of `radius`
+    pub fn radius(&mut self, radius: f32) -> &mut Self {
+        self.size = Vec2::splat(radius * 2.0);
+        self
+    }
+}
+
+placement!(Ellipse);
+size!(Ellipse);
+fill_and_stroke!(Ellipse);
+
+/// A rectangle centred on its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rectangle {
+    pub position: Point2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_weight: f32,
+}
+
+impl Default for Rectangle {
+    fn default() -> Self {
+        Rectangle {
+            position: Point2::ZERO,
+            size: Vec2::splat(100.0),
+            rotation: 0.0,
+            fill: Some(white()),
+            stroke: None,
+            stroke_weight: 1.0,
+        }
+    }
+}
+
+impl Rectangle {
+    /// Place and size the rectangle to cover `rect`
+    pub fn rect(&mut self, rect: Rect) -> &mut Self {
+        self.position = rect.xy();
+        self.size = rect.wh();
+        self
+    }
+}
+
+placement!(Rectangle);
+size!(Rectangle);
+fill_and_stroke!(Rectangle);
+
+/// A straight line between two points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub start: Point2,
+    pub end: Point2,
+    pub weight: f32,
+    pub color: Color,
+    /// Whether the ends are rounded off rather than cut square
+    pub round_caps: bool,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Line {
+            start: Point2::ZERO,
+            end: Point2::ZERO,
+            weight: 1.0,
+            color: white(),
+            round_caps: false,
+        }
+    }
+}
+
+impl Line {
+    pub fn start(&mut self, start: Point2) -> &mut Self {
+        self.start = start;
+        self
+    }
+
+    pub fn end(&mut self, end: Point2) -> &mut Self {
+        self.end = end;
+        self
+    }
+
+    pub fn caps_round(&mut self) -> &mut Self {
+        self.round_caps = true;
+        self
+    }
+}
+
+line_style!(Line);
+
+/// An open line through a list of points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Point2>,
+    pub weight: f32,
+    pub color: Color,
+}
+
+impl Default for Polyline {
+    fn default() -> Self {
+        Polyline {
+            points: vec![],
+            weight: 1.0,
+            color: white(),
+        }
+    }
+}
+
+impl Polyline {
+    pub fn points(&mut self, points: impl IntoIterator<Item = Point2>) -> &mut Self {
+        self.points = points.into_iter().collect();
+        self
+    }
+}
+
+line_style!(Polyline);
+
+/// A line with a triangular head at its end
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arrow {
+    pub start: Point2,
+    pub end: Point2,
+    pub weight: f32,
+    pub color: Color,
+    /// Half the width of the head (twice the weight if not given, as in nannou)
+    pub head_width: Option<f32>,
+    /// The length of the head (four times the weight if not given, as in nannou)
+    pub head_length: Option<f32>,
+    pub round_start: bool,
+}
+
+impl Default for Arrow {
+    fn default() -> Self {
+        Arrow {
+            start: Point2::ZERO,
+            end: Point2::ZERO,
+            weight: 1.0,
+            color: white(),
+            head_width: None,
+            head_length: None,
+            round_start: false,
+        }
+    }
+}
+
+impl Arrow {
+    pub fn start(&mut self, start: Point2) -> &mut Self {
+        self.start = start;
+        self
+    }
+
+    pub fn end(&mut self, end: Point2) -> &mut Self {
+        self.end = end;
+        self
+    }
+
+    pub fn head_width(&mut self, width: f32) -> &mut Self {
+        self.head_width = Some(width);
+        self
+    }
+
+    pub fn head_length(&mut self, length: f32) -> &mut Self {
+        self.head_length = Some(length);
+        self
+    }
+
+    pub fn start_cap_round(&mut self) -> &mut Self {
+        self.round_start = true;
+        self
+    }
+
+    /// The line and the three corners of the head, tip first, as nannou draws them.
+    ///
+    /// The line is `None` when the head takes up the whole arrow.
+    pub fn geometry(&self) -> (Option<(Point2, Point2)>, [Point2; 3]) {
+        let head_width = self.head_width.unwrap_or(self.weight * 2.0);
+        let head_length = self.head_length.unwrap_or(self.weight * 4.0);
+        let direction = self.end - self.start;
+        let length = direction.length();
+        let head_length = head_length.min(length);
+        let along = direction.normalize_or_zero() * head_length;
+        let across = along.perp().normalize_or_zero() * head_width;
+        let base = self.end - along;
+        let line = (length > head_length).then_some((self.start, base));
+        (line, [self.end, base + across, base - across])
+    }
+}
+
+line_style!(Arrow);
+
+/// Text laid out in a box centred on its position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub text: String,
+    pub position: Point2,
+    /// The size of the box the text is laid out and wrapped in
+    pub size: Vec2,
+    pub rotation: f32,
+    pub font_size: FontSize,
+    pub color: Color,
+    pub justify: Justify,
+    pub align: Align,
+}
+
+impl Text {
+    pub fn font_size(&mut self, font_size: FontSize) -> &mut Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn color(&mut self, color: impl IntoLinSrgba<f32>) -> &mut Self {
+        self.color = color.into_lin_srgba();
+        self
+    }
+
+    pub fn left_justify(&mut self) -> &mut Self {
+        self.justify = Justify::Left;
+        self
+    }
+
+    pub fn right_justify(&mut self) -> &mut Self {
+        self.justify = Justify::Right;
+        self
+    }
+
+    pub fn align_text_top(&mut self) -> &mut Self {
+        self.align = Align::End;
+        self
+    }
+
+    pub fn align_text_bottom(&mut self) -> &mut Self {
+        self.align = Align::Start;
+        self
+    }
+
+    /// Lay the text out in nannou's coordinates, with the box centred on the origin
+    pub fn layout(&self) -> nannou::text::Text<'_> {
+        nannou::text::text(&self.text)
+            .font_size(self.font_size)
+            .justify(self.justify)
+            .y_align(self.align)
+            .build(Rect::from_wh(self.size))
+    }
+}
+
+placement!(Text);
+size!(Text);
+
+/// Push a default `$variant` onto the scene and return it to be built
+macro_rules! push {
+    ($scene:expr, $variant:ident) => {{
+        $scene.shapes.push(Shape::$variant(Default::default()));
+        match $scene.shapes.last_mut() {
+            Some(Shape::$variant(shape)) => shape,
+            _ => unreachable!(),
+        }
+    }};
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clear the frame to `color` before drawing the shapes
+    pub fn background(&mut self, color: impl IntoLinSrgba<f32>) {
+        self.background = Some(color.into_lin_srgba());
+    }
+
+    pub fn polygon(&mut self) -> &mut Polygon {
+        push!(self, Polygon)
+    }
+
+    pub fn ellipse(&mut self) -> &mut Ellipse {
+        push!(self, Ellipse)
+    }
+
+    pub fn rect(&mut self) -> &mut Rectangle {
+        push!(self, Rectangle)
+    }
+
+    pub fn line(&mut self) -> &mut Line {
+        push!(self, Line)
+    }
+
+    pub fn polyline(&mut self) -> &mut Polyline {
+        push!(self, Polyline)
+    }
+
+    pub fn arrow(&mut self) -> &mut Arrow {
+        push!(self, Arrow)
+    }
+
+    /// Text in a 200 by 200 box, centred and in the default font size, as in nannou
+    pub fn text(&mut self, text: &str) -> &mut Text {
+        self.shapes.push(Shape::Text(Text {
+            text: text.to_owned(),
+            position: Point2::ZERO,
+            size: Vec2::splat(200.0),
+            rotation: 0.0,
+            font_size: 12,
+            color: white(),
+            justify: Justify::Center,
+            align: Align::Middle,
+        }));
+        match self.shapes.last_mut() {
+            Some(Shape::Text(text)) => text,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Draw the scene with nannou
+    pub fn draw(&self, draw: &nannou::Draw) {
+        if let Some(background) = self.background {
+            draw.background().color(background);
+        }
+        for shape in &self.shapes {
+            match shape {
+                Shape::Polygon(polygon) => {
+                    let points = || polygon.points.iter().copied();
+                    if let Some(fill) = polygon.fill {
+                        draw.polygon()
+                            .points(points())
+                            .xy(polygon.position)
+                            .rotate(polygon.rotation)
+                            .color(fill);
+                    }
+                    if let Some(stroke) = polygon.stroke {
+                        draw.polyline()
+                            .weight(polygon.stroke_weight)
+                            .points_closed(points())
+                            .xy(polygon.position)
+                            .rotate(polygon.rotation)
+                            .color(stroke);
+                    }
+                }
+                Shape::Ellipse(ellipse) => {
+                    let drawing = draw
+                        .ellipse()
+                        .xy(ellipse.position)
+                        .wh(ellipse.size)
+                        .rotate(ellipse.rotation);
+                    let drawing = match ellipse.fill {
+                        Some(fill) => drawing.color(fill),
+                        None => drawing.no_fill(),
+                    };
+                    if let Some(stroke) = ellipse.stroke {
+                        drawing.stroke_weight(ellipse.stroke_weight).stroke(stroke);
+                    }
+                }
+                Shape::Rectangle(rect) => {
+                    let drawing = draw
+                        .rect()
+                        .xy(rect.position)
+                        .wh(rect.size)
+                        .rotate(rect.rotation);
+                    let drawing = match rect.fill {
+                        Some(fill) => drawing.color(fill),
+                        None => drawing.no_fill(),
+                    };
+                    if let Some(stroke) = rect.stroke {
+                        drawing.stroke_weight(rect.stroke_weight).stroke(stroke);
+                    }
+                }
+                Shape::Line(line) => {
+                    let drawing = draw
+                        .line()
+                        .start(line.start)
+                        .end(line.end)
+                        .weight(line.weight)
+                        .color(line.color);
+                    if line.round_caps {
+                        drawing.caps_round();
+                    }
+                }
+                Shape::Polyline(polyline) => {
+                    draw.polyline()
+                        .weight(polyline.weight)
+                        .points(polyline.points.iter().copied())
+                        .color(polyline.color);
+                }
+                Shape::Arrow(arrow) => {
+                    let mut drawing = draw
+                        .arrow()
+                        .start(arrow.start)
+                        .end(arrow.end)
+                        .weight(arrow.weight)
+                        .color(arrow.color);
+                    if let Some(width) = arrow.head_width {
+                        drawing = drawing.head_width(width);
+                    }
+                    if let Some(length) = arrow.head_length {
+                        drawing = drawing.head_length(length);
+                    }
+                    if arrow.round_start {
+                        drawing.start_cap_round();
+                    }
+                }
+                Shape::Text(text) => {
+                    draw.text(&text.text)
+                        .xy(text.position)
+                        .wh(text.size)
+                        .rotate(text.rotation)
+                        .font_size(text.font_size)
+                        .justify(text.justify)
+                        .y_align_text(text.align)
+                        .color(text.color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+/// The `--seed` command line argument, to flatten into a sketch's arguments
+#[derive(Debug, Clone, clap::Args)]
+pub struct SeedArgs {
+    /// Seed for the random number generator, to reproduce a previous run (random if not given)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+impl SeedArgs {
+    /// The random number generator for the run.
+    ///
+    /// The seed is printed so that any run can be reproduced by passing it back with `--seed`.
+    pub fn rng(&self) -> StdRng {
+        let seed = self.seed.unwrap_or_else(rand::random);
+        println!("seed: {}", seed);
+        StdRng::seed_from_u64(seed)
+    }
+}
@@ -0,0 +1,64 @@
+//! Serde helpers for the nannou types that don't implement serde themselves
+
+/// (De)serialize a `Vec2` as `[x, y]`
+pub mod vec2 {
+    use nannou::prelude::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [v.x, v.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec2, D::Error> {
+        let [x, y] = <[f32; 2]>::deserialize(deserializer)?;
+        Ok(Vec2::new(x, y))
+    }
+}
+
+/// (De)serialize a `Vec<Vec2>` as `[[x, y], ...]`
+pub mod vec2s {
+    use nannou::prelude::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(vs: &[Vec2], serializer: S) -> Result<S::Ok, S::Error> {
+        vs.iter()
+            .map(|v| [v.x, v.y])
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec2>, D::Error> {
+        let vs = Vec::<[f32; 2]>::deserialize(deserializer)?;
+        Ok(vs.into_iter().map(|[x, y]| Vec2::new(x, y)).collect())
+    }
+}
+
+/// (De)serialize an `Rgba<f32>` as `[red, green, blue, alpha]`
+pub mod rgba {
+    use nannou::color::Rgba;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Rgba<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+        [c.red, c.green, c.blue, c.alpha].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgba<f32>, D::Error> {
+        let [red, green, blue, alpha] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(nannou::color::rgba(red, green, blue, alpha))
+    }
+}
+
+/// (De)serialize an `Rgb<u8>` as `[red, green, blue]`
+pub mod rgb8 {
+    use nannou::color::Rgb;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        [c.red, c.green, c.blue].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Rgb<u8>, D::Error> {
+        let [red, green, blue] = <[u8; 3]>::deserialize(deserializer)?;
+        Ok(nannou::color::rgb(red, green, blue))
+    }
+}
@@ -0,0 +1,281 @@
+//! Writing a [`Scene`] as an SVG, for print work.
+//!
+//! Shapes keep their colours, alpha, stroke weights and rotations. Text is written as the
+//! outlines of its glyphs, so the SVG doesn't depend on the fonts installed where it is opened.
+
+use crate::scene::{srgba, Color, Scene, Shape};
+use nannou::geom::{Point2, Rect};
+use nannou::lyon::path::PathEvent;
+use std::{fmt::Write, fs, io, path::Path};
+
+/// The scene as an SVG document, showing the window covering `bounds`.
+///
+/// A scene without a background is drawn over the frames before it in a window, but stands on
+/// its own here.
+pub fn to_svg(scene: &Scene, bounds: Rect) -> String {
+    let (w, h) = (bounds.w(), bounds.h());
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+    )
+    .unwrap();
+    if let Some(background) = scene.background {
+        writeln!(
+            svg,
+            r#"<rect width="{w}" height="{h}" {}/>"#,
+            paint("fill", background)
+        )
+        .unwrap();
+    }
+    // Draw in nannou's coordinates: origin at the centre of the window, y up
+    writeln!(
+        svg,
+        r#"<g transform="translate({} {}) scale(1 -1)">"#,
+        w / 2.0 - bounds.x(),
+        h / 2.0 + bounds.y()
+    )
+    .unwrap();
+    for shape in &scene.shapes {
+        write_shape(&mut svg, shape);
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Write the scene to an SVG file
+pub fn save(scene: &Scene, bounds: Rect, path: &Path) -> io::Result<()> {
+    fs::write(path, to_svg(scene, bounds)).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("could not write {}: {}", path.display(), e),
+        )
+    })
+}
+
+fn write_shape(svg: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Polygon(polygon) => writeln!(
+            svg,
+            r#"<polygon points="{}" transform="{}" fill-rule="evenodd" {}/>"#,
+            points(&polygon.points),
+            transform(polygon.position, polygon.rotation),
+            fill_and_stroke(polygon.fill, polygon.stroke, polygon.stroke_weight)
+        ),
+        Shape::Ellipse(ellipse) => writeln!(
+            svg,
+            r#"<ellipse rx="{}" ry="{}" transform="{}" {}/>"#,
+            ellipse.size.x / 2.0,
+            ellipse.size.y / 2.0,
+            transform(ellipse.position, ellipse.rotation),
+            fill_and_stroke(ellipse.fill, ellipse.stroke, ellipse.stroke_weight)
+        ),
+        Shape::Rectangle(rect) => writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" transform="{}" {}/>"#,
+            -rect.size.x / 2.0,
+            -rect.size.y / 2.0,
+            rect.size.x,
+            rect.size.y,
+            transform(rect.position, rect.rotation),
+            fill_and_stroke(rect.fill, rect.stroke, rect.stroke_weight)
+        ),
+        Shape::Line(line) => writeln!(
+            svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-linecap="{}"/>"#,
+            line.start.x,
+            line.start.y,
+            line.end.x,
+            line.end.y,
+            stroke(line.color, line.weight),
+            if line.round_caps { "round" } else { "butt" }
+        ),
+        Shape::Polyline(polyline) => writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" {} stroke-linejoin="round"/>"#,
+            points(&polyline.points),
+            stroke(polyline.color, polyline.weight)
+        ),
+        Shape::Arrow(arrow) => {
+            let (line, head) = arrow.geometry();
+            if let Some((start, end)) = line {
+                writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-linecap="{}"/>"#,
+                    start.x,
+                    start.y,
+                    end.x,
+                    end.y,
+                    stroke(arrow.color, arrow.weight),
+                    if arrow.round_start { "round" } else { "butt" }
+                )
+                .unwrap();
+            }
+            writeln!(
+                svg,
+                r#"<polygon points="{}" {}/>"#,
+                points(&head),
+                paint("fill", arrow.color)
+            )
+        }
+        Shape::Text(text) => {
+            let mut d = String::new();
+            for event in text.layout().path_events() {
+                match event {
+                    PathEvent::Begin { at } => write!(d, "M{} {}", at.x, at.y),
+                    PathEvent::Line { to, .. } => write!(d, "L{} {}", to.x, to.y),
+                    PathEvent::Quadratic { ctrl, to, .. } => {
+                        write!(d, "Q{} {} {} {}", ctrl.x, ctrl.y, to.x, to.y)
+                    }
+                    PathEvent::Cubic {
+                        ctrl1, ctrl2, to, ..
+                    } => write!(
+                        d,
+                        "C{} {} {} {} {} {}",
+                        ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+                    ),
+                    PathEvent::End { close: true, .. } => write!(d, "Z"),
+                    PathEvent::End { close: false, .. } => Ok(()),
+                }
+                .unwrap();
+            }
+            writeln!(
+                svg,
+                r#"<path d="{}" transform="{}" {}/>"#,
+                d,
+                transform(text.position, text.rotation),
+                paint("fill", text.color)
+            )
+        }
+    }
+    .unwrap();
+}
+
+fn points(points: &[Point2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Move to `position`, then turn anticlockwise by `rotation` radians
+fn transform(position: Point2, rotation: f32) -> String {
+    format!(
+        "translate({} {}) rotate({})",
+        position.x,
+        position.y,
+        rotation.to_degrees()
+    )
+}
+
+/// A `fill` or `stroke` attribute, and its opacity if the colour isn't opaque
+fn paint(attribute: &str, color: Color) -> String {
+    let c = srgba(color);
+    let byte = |channel: f32| (channel * 255.0).round() as u8;
+    let mut paint = format!(
+        r#"{}="rgb({},{},{})""#,
+        attribute,
+        byte(c.red),
+        byte(c.green),
+        byte(c.blue)
+    );
+    if c.alpha < 1.0 {
+        write!(paint, r#" {}-opacity="{}""#, attribute, c.alpha).unwrap();
+    }
+    paint
+}
+
+fn stroke(color: Color, weight: f32) -> String {
+    format!(r#"{} stroke-width="{}""#, paint("stroke", color), weight)
+}
+
+fn fill_and_stroke(fill: Option<Color>, stroke_color: Option<Color>, weight: f32) -> String {
+    let fill = match fill {
+        Some(fill) => paint("fill", fill),
+        None => r#"fill="none""#.to_owned(),
+    };
+    match stroke_color {
+        Some(color) => format!("{} {}", fill, stroke(color, weight)),
+        None => fill,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nannou::color::{rgba, BLUE, RED};
+    use std::f32::consts::PI;
+
+    /// The SVG element drawn for a scene of just one shape
+    fn element(scene: &Scene) -> String {
+        let svg = to_svg(scene, Rect::from_w_h(100.0, 100.0));
+        let lines: Vec<&str> = svg.lines().collect();
+        assert_eq!(lines.len(), 5, "expected one shape in {}", svg);
+        lines[2].to_owned()
+    }
+
+    #[test]
+    fn shapes_without_a_stroke_are_only_filled() {
+        let mut scene = Scene::new();
+        scene
+            .ellipse()
+            .w_h(10.0, 20.0)
+            .color(RED)
+            .stroke_weight(3.0);
+        let ellipse = element(&scene);
+        assert!(ellipse.contains(r#"fill="rgb(255,0,0)""#), "{}", ellipse);
+        assert!(!ellipse.contains("opacity"), "{}", ellipse);
+        assert!(!ellipse.contains("stroke"), "{}", ellipse);
+    }
+
+    #[test]
+    fn shapes_with_a_stroke_are_outlined() {
+        let mut scene = Scene::new();
+        scene
+            .rect()
+            .w_h(10.0, 20.0)
+            .color(rgba(1.0, 0.0, 0.0, 0.5))
+            .stroke(BLUE)
+            .stroke_weight(3.0);
+        let rect = element(&scene);
+        assert!(
+            rect.contains(r#"fill="rgb(255,0,0)" fill-opacity="0.5""#),
+            "{}",
+            rect
+        );
+        assert!(
+            rect.contains(r#"stroke="rgb(0,0,255)" stroke-width="3""#),
+            "{}",
+            rect
+        );
+
+        let mut scene = Scene::new();
+        scene.rect().w_h(10.0, 20.0).no_fill().stroke(BLUE);
+        let outline = element(&scene);
+        assert!(outline.contains(r#"fill="none""#), "{}", outline);
+        assert!(outline.contains(r#"stroke="rgb(0,0,255)""#), "{}", outline);
+    }
+
+    #[test]
+    fn shapes_are_placed_then_turned() {
+        let mut scene = Scene::new();
+        scene
+            .rect()
+            .x_y(10.0, -20.0)
+            .w_h(4.0, 2.0)
+            .rotate(PI / 2.0)
+            .color(RED);
+        let rect = element(&scene);
+        assert!(
+            rect.contains(r#"x="-2" y="-1" width="4" height="2""#),
+            "{}",
+            rect
+        );
+        assert!(
+            rect.contains(r#"transform="translate(10 -20) rotate(90)""#),
+            "{}",
+            rect
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+/// Steps a simulation at a fixed rate, however often (or unevenly) the app updates.
+///
+/// Time from each update is added to an accumulator, and whole steps are taken out of it. The
+/// fraction of a step left over is used to draw the simulation between its last two steps.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    /// The most steps taken in one update, so that a long stall doesn't make the simulation
+    /// fall further and further behind trying to catch up
+    pub max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// A timestep running `rate` steps per second
+    ///
+    /// # Panics
+    ///
+    /// If `rate` isn't a finite number more than zero
+    pub fn new(rate: f64) -> Self {
+        assert!(
+            rate.is_finite() && rate > 0.0,
+            "a fixed timestep needs a finite rate more than zero, not {}",
+            rate
+        );
+        FixedTimestep {
+            step: Duration::from_secs_f64(1.0 / rate),
+            accumulator: Duration::ZERO,
+            max_steps: 8,
+        }
+    }
+
+    /// The length of a step in seconds
+    pub fn dt(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Add the time since the last update, and return how many steps to take now
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps > self.max_steps {
+            // Drop the time we can't catch up on
+            self.accumulator = Duration::ZERO;
+            steps = self.max_steps;
+        }
+        steps
+    }
+
+    /// How far through the next step the accumulated time is, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_steps_are_taken_and_the_rest_is_carried_over() {
+        let mut timestep = FixedTimestep::new(100.0);
+        assert_eq!(timestep.advance(Duration::from_millis(25)), 2);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(Duration::from_millis(5)), 1);
+        assert!(timestep.alpha().abs() < 1e-4);
+        assert_eq!(timestep.advance(Duration::from_millis(3)), 0);
+        assert!((timestep.alpha() - 0.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn a_stall_drops_the_steps_it_cannot_catch_up_on() {
+        let mut timestep = FixedTimestep::new(60.0);
+        assert_eq!(timestep.advance(Duration::from_secs(1)), timestep.max_steps);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than zero")]
+    fn a_rate_of_zero_is_refused() {
+        FixedTimestep::new(0.0);
+    }
+}
@@ -1,4 +1,11 @@
+use clap::Parser;
 use nannou::prelude::*;
+use sketch::{
+    record::{self, RecordArgs, Recorder},
+    scene::Scene,
+    Sketch,
+};
+use std::time::Duration;
 fn build_mst(nodes: &Vec<Node>) -> Vec<Edge> {
     // let mut edges = Vec::new();
     // for node1 in 0..nodes.len() {
@@ -37,6 +44,7 @@ fn build_mst(nodes: &Vec<Node>) -> Vec<Edge> {
     // mst
     let mut edges = Vec::new();
     for node in nodes {
+        #[allow(clippy::single_match)]
         match nodes
             .iter()
             .filter(|node2| node2.ring == node.ring + 1)
@@ -51,7 +59,22 @@ fn build_mst(nodes: &Vec<Node>) -> Vec<Edge> {
     edges
 }
 
+/// Nodes turning in rings, joined by legs
+#[derive(Debug, Parser)]
+struct Args {
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
 fn main() {
+    let args = Args::parse();
+    if args.record.headless {
+        if let Err(e) = record::run_headless(&mut Model::new(), &args.record) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     nannou::app(model)
         .update(update)
         .event(event)
@@ -59,6 +82,9 @@ fn main() {
         .run();
 }
 
+/// How fast the nodes turn about the centre, in radians per second
+const TURN_RATE: f32 = 0.6;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct Edge {
     a: Node,
@@ -74,39 +100,62 @@ struct Node {
 
 struct Model {
     nodes: Vec<Node>,
+    recorder: Recorder,
 }
 
-fn model(_app: &App) -> Model {
-    let rings = 5;
-    let sectors = 10;
-    let mut nodes = Vec::new();
-    for i in 0..rings {
-        for k in 1..sectors+1 {
-            nodes.push(Node {
-                pos: pt2(40.0 + 100.0 * i as f32, 40.0 + 100.0 * i as f32)
-                    .rotate(2.0 * PI / rings as f32 * (k + i) as f32),
-                direction: (i % 2) as f32 * 2.0 - 1.0,
-                ring: i,
-                sector: k,
-            });
+impl Model {
+    fn new() -> Self {
+        let rings = 5;
+        let sectors = 10;
+        let mut nodes = Vec::new();
+        for i in 0..rings {
+            for k in 1..sectors + 1 {
+                nodes.push(Node {
+                    pos: pt2(40.0 + 100.0 * i as f32, 40.0 + 100.0 * i as f32)
+                        .rotate(2.0 * PI / rings as f32 * (k + i) as f32),
+                    direction: (i % 2) as f32 * 2.0 - 1.0,
+                    ring: i,
+                    sector: k,
+                });
+            }
+        }
+        nodes.push(Node {
+            pos: pt2(0.0, 0.0),
+            direction: 0.0,
+            ring: 0,
+            sector: 0,
+        });
+        Model {
+            nodes,
+            recorder: Recorder::default(),
         }
     }
-    nodes.push(Node {
-        pos: pt2(0.0, 0.0),
-        direction: 0.0,
-        ring: 0,
-        sector: 0,
+}
+
+fn model(_app: &App) -> Model {
+    let args = Args::parse();
+    let mut model = Model::new();
+    model.recorder = Recorder::new(&args.record).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     });
-    Model { nodes }
+    model
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    for node in model.nodes.iter_mut() {
-        node.pos = node.pos.rotate(0.01 * node.direction);
-    }
+fn update(app: &App, model: &mut Model, update: Update) {
+    let dt = model.recorder.frame_time(app, update.since_last);
+    model.advance(app.window_rect(), dt);
 }
 
-fn event(_app: &App, _model: &mut Model, _event: Event) {}
+fn event(_app: &App, model: &mut Model, event: Event) {
+    if let Event::WindowEvent {
+        simple: Some(KeyPressed(Key::S)),
+        ..
+    } = event
+    {
+        model.recorder.request_svg();
+    }
+}
 
 // fn draw_arc(draw: &Draw, r: f32, theta0: f32, theta1: f32) {
 //     let n = 100;
@@ -122,53 +171,74 @@ fn event(_app: &App, _model: &mut Model, _event: Event) {}
 //         .color(WHITE);
 // }
 
+impl Sketch for Model {
+    /// The nodes turn at a fixed rate
+    fn advance(&mut self, _bounds: Rect, dt: Duration) {
+        for node in self.nodes.iter_mut() {
+            node.pos = node
+                .pos
+                .rotate(TURN_RATE * node.direction * dt.as_secs_f32());
+        }
+    }
+
+    fn scene(&self, _bounds: Rect) -> Scene {
+        let mut scene = Scene::new();
+        scene.background(BLACK);
+        let mst = build_mst(&self.nodes);
+        let edge = mst[0];
+        let a = edge.a.pos;
+        let b = edge.b.pos;
+        scene
+            .line()
+            .start(a)
+            .end(b)
+            .stroke_weight(10.0)
+            .color(rgb(1.0, 0.0, 1.0));
+        // for node in self.nodes.iter() {
+        //     draw.ellipse().xy(node.pos).radius(5.0).color(WHITE);
+        // }
+        // for edge in mst.iter() {
+        //     let node1 = edge.a;
+        //     let node2 = edge.b;
+        //     let (inner_node, outer_node) = if node1.pos.length() < node2.pos.length() {
+        //         (node1, node2)
+        //     } else {
+        //         (node2, node1)
+        //     };
+        //     let r = inner_node.pos.length()
+        //         + (outer_node.pos.length() - inner_node.pos.length())
+        //             * inner_node.sector.abs_diff(outer_node.sector) as f32
+        //             / (inner_node.sector + outer_node.sector) as f32;
+        //     draw.line()
+        //         .start(inner_node.pos)
+        //         .end(inner_node.pos.normalize() * r)
+        //         .stroke_weight(1.0)
+        //         .color(WHITE);
+        //     let theta0 = inner_node.pos.angle();
+        //     let theta1 = outer_node.pos.angle();
+        //     draw_arc(&draw, r, theta0.min(theta1), theta1.max(theta0));
+        //     draw.line()
+        //         .start(
+        //             inner_node
+        //                 .pos
+        //                 .rotate(outer_node.pos.angle() - inner_node.pos.angle())
+        //                 .normalize()
+        //                 * r,
+        //         )
+        //         .end(outer_node.pos)
+        //         .stroke_weight(1.0)
+        //         .color(WHITE);
+        // }
+        scene
+    }
+}
+
 fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
-    draw.background().color(BLACK);
-    let mst = build_mst(&model.nodes);
-    let edge = mst[0];
-    println!("{:?} -> {:?}", edge.a.pos, edge.b.pos);
-    let a = edge.a.pos.clone();
-    let b = edge.b.pos.clone();
-    draw.line()
-        .start(a)
-        .end(b)
-        .stroke_weight(10.0)
-        .color(rgb(1.0, 0.0, 1.0));
-    // for node in model.nodes.iter() {
-    //     draw.ellipse().xy(node.pos).radius(5.0).color(WHITE);
-    // }
-    // for edge in mst.iter() {
-    //     let node1 = edge.a;
-    //     let node2 = edge.b;
-    //     let (inner_node, outer_node) = if node1.pos.length() < node2.pos.length() {
-    //         (node1, node2)
-    //     } else {
-    //         (node2, node1)
-    //     };
-    //     let r = inner_node.pos.length()
-    //         + (outer_node.pos.length() - inner_node.pos.length())
-    //             * inner_node.sector.abs_diff(outer_node.sector) as f32
-    //             / (inner_node.sector + outer_node.sector) as f32;
-    //     draw.line()
-    //         .start(inner_node.pos)
-    //         .end(inner_node.pos.normalize() * r)
-    //         .stroke_weight(1.0)
-    //         .color(WHITE);
-    //     let theta0 = inner_node.pos.angle();
-    //     let theta1 = outer_node.pos.angle();
-    //     draw_arc(&draw, r, theta0.min(theta1), theta1.max(theta0));
-    //     draw.line()
-    //         .start(
-    //             inner_node
-    //                 .pos
-    //                 .rotate(outer_node.pos.angle() - inner_node.pos.angle())
-    //                 .normalize()
-    //                 * r,
-    //         )
-    //         .end(outer_node.pos)
-    //         .stroke_weight(1.0)
-    //         .color(WHITE);
-    // }
+    let scene = model.scene(frame.rect());
+    scene.draw(&draw);
+    if let Err(e) = model.recorder.save_svg(&scene, frame.rect()) {
+        eprintln!("error: {}", e);
+    }
     draw.to_frame(app, &frame).unwrap();
 }